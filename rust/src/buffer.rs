@@ -0,0 +1,688 @@
+//! The text being edited: its contents, file name, syntax, undo history and dirty state.
+//!
+//! The contents live in a [`PieceTable`]. Rows are not stored: [`Buffer::row`] cuts the line
+//! out of the table and renders and highlights it when it is asked for, which is only ever
+//! for the rows on screen. The one thing kept per row is whether it ends inside a multi line
+//! comment, worked out up to the furthest row drawn so far.
+
+use std::cell::RefCell;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Range;
+use std::os::unix::{self, fs::MetadataExt};
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
+
+use crate::cursor::Cursor;
+use crate::data;
+use crate::history::{self, Edit, EditKind, History, Move, Travel};
+use crate::piece_table::PieceTable;
+use crate::row::Row;
+use crate::syntax::{self, Syntax};
+use crate::TAB_STOP;
+
+/// Files at least this big are mapped into memory and loaded in the background instead of read.
+const MMAP_MIN_LEN: u64 = 8 << 20;
+
+#[derive(Debug, Default)]
+pub struct Buffer {
+    text: PieceTable, // every row followed by '\n', so the text is empty or ends with one
+    open_comments: RefCell<Vec<bool>>, // whether each row ends inside a multi line comment, for a prefix of the rows
+    history: History,
+    saved: usize, // history state when the file was last saved or opened
+    changes: usize, // how many times the text has been edited, not counting the rows loaded
+    filename: Option<String>,
+    syntax: Option<&'static Syntax>,
+}
+
+impl Buffer {
+    pub fn new() -> Buffer {
+        Buffer::default()
+    }
+
+    /// Opens `filename` in a new buffer.
+    ///
+    /// Big files are mapped into memory rather than read, and only their first few megabytes are
+    /// there right away. The rest comes in through [`Buffer::poll_loading`], and the buffer is
+    /// read-only until it has. The file must not be truncated by another program while it is open:
+    /// reading the part that is gone kills the process with SIGBUS, see [`TerminalGuard`](crate::TerminalGuard).
+    pub fn open(filename: &str) -> io::Result<Buffer> {
+        let mut file = File::open(filename)?;
+        let mut buffer = if file.metadata()?.len() >= MMAP_MIN_LEN {
+            let map = unsafe { Mmap::map(&file)? };
+            let mut buffer = Buffer {
+                text: PieceTable::mapped(map),
+                ..Buffer::default()
+            };
+            if !buffer.is_loading() {
+                buffer.end_with_newline();
+            }
+            buffer
+        } else {
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)?;
+            Buffer::from_vec(contents)
+        };
+        buffer.set_filename(filename);
+        Ok(buffer)
+    }
+
+    /// Builds a clean, unnamed buffer from file contents.
+    pub fn from_bytes(contents: &[u8]) -> Buffer {
+        Buffer::from_vec(contents.to_vec())
+    }
+
+    fn from_vec(contents: Vec<u8>) -> Buffer {
+        let mut buffer = Buffer {
+            text: PieceTable::new(contents),
+            ..Buffer::default()
+        };
+        buffer.end_with_newline();
+        buffer
+    }
+
+    /// Gives the last row a newline if it has none, the way every row gets one when saved.
+    ///
+    /// In the text as it was loaded, the newline is part of the state the history starts from, so
+    /// undo never takes it away. After edits, which a buffer still loading can have outside the
+    /// editor, it is an edit like any other.
+    fn end_with_newline(&mut self) {
+        let len = self.text.len();
+        if len == 0 || self.text.byte(len - 1) == Some(b'\n') {
+            return;
+        }
+        match self.history.state() == 0 && !self.history.has_pending() && self.changes == 0 {
+            true => self.text.insert(len, b"\n"),
+            false => self.replace(len..len, b"\n"),
+        }
+    }
+
+    pub fn text(&self) -> &PieceTable {
+        &self.text
+    }
+
+    /// Returns row `at`, rendered and highlighted.
+    pub fn row(&self, at: usize) -> Option<Row> {
+        if at >= self.numrows() {
+            return None;
+        }
+        let mut row = Row::new(&self.text.slice(self.row_range(at)));
+        if let Some(syntax) = self.syntax {
+            let in_comment = self.open_comment_before(at);
+            row.hl_open_comment = syntax::highlight_row(syntax, &row.render, &mut row.hl, in_comment);
+        }
+        Some(row)
+    }
+
+    /// Returns row `at` rendered but not highlighted, for working out where its clusters and
+    /// columns are without going back through the rows above for an open comment.
+    pub fn plain_row(&self, at: usize) -> Option<Row> {
+        Some(Row::new(&self.row_chars(at)?))
+    }
+
+    /// Returns the bytes of row `at` as they are in the file, without rendering or highlighting it.
+    pub fn row_chars(&self, at: usize) -> Option<Vec<u8>> {
+        if at >= self.numrows() {
+            return None;
+        }
+        Some(self.text.slice(self.row_range(at)))
+    }
+
+    /// Returns the length of row `at` in bytes, or 0 if there is no such row.
+    pub fn row_len(&self, at: usize) -> usize {
+        if at >= self.numrows() {
+            return 0;
+        }
+        self.row_range(at).len()
+    }
+
+    /// Returns the number of rows. While a file is loading, these are its complete lines so far.
+    pub fn numrows(&self) -> usize {
+        self.text.line_count() - 1
+    }
+
+    /// Whether part of the file is still being loaded, which makes the buffer read-only.
+    pub fn is_loading(&self) -> bool {
+        self.text.is_loading()
+    }
+
+    /// Returns how much of the file has been loaded, in percent, while it is loading.
+    pub fn loading_progress(&self) -> Option<usize> {
+        let (done, total) = self.text.loading_progress()?;
+        Some(done * 100 / total.max(1))
+    }
+
+    /// Adds the rows loaded since the last call. Returns whether there are any.
+    pub fn poll_loading(&mut self) -> bool {
+        if !self.text.poll_loading() {
+            return false;
+        }
+        if !self.text.is_loading() {
+            self.end_with_newline();
+        }
+        true
+    }
+
+    /// Counts the edits to the text so far, so that what was found in it can be kept until it changes again.
+    pub fn changes(&self) -> usize {
+        self.changes
+    }
+
+    /// Whether the text differs from the file, which undoing back to where it was saved puts right.
+    pub fn is_dirty(&self) -> bool {
+        self.history.has_pending() || self.history.state() != self.saved
+    }
+
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Renames the buffer and picks the syntax highlighting that goes with the new name.
+    pub fn set_filename(&mut self, filename: &str) {
+        self.filename = Some(filename.to_string());
+        self.select_syntax_highlight();
+    }
+
+    pub fn syntax(&self) -> Option<&'static Syntax> {
+        self.syntax
+    }
+
+    /*** syntax highlighting ***/
+
+    /// Returns whether row `at - 1` ends inside a multi line comment, highlighting the rows
+    /// before it that haven't been yet.
+    fn open_comment_before(&self, at: usize) -> bool {
+        let syntax = match self.syntax {
+            Some(syntax) if at > 0 && !syntax.multiline_comment_start.is_empty() => syntax,
+            _ => return false,
+        };
+        let mut open_comments = self.open_comments.borrow_mut();
+        while open_comments.len() < at {
+            let filerow = open_comments.len();
+            let in_comment = open_comments.last().copied().unwrap_or(false);
+            let mut row = Row::new(&self.text.slice(self.row_range(filerow)));
+            open_comments.push(syntax::highlight_row(syntax, &row.render, &mut row.hl, in_comment));
+        }
+        open_comments[at - 1]
+    }
+
+    /// Forgets the comment state from row `at` on, after row `at` has changed.
+    fn invalidate(&mut self, at: usize) {
+        self.open_comments.get_mut().truncate(at);
+    }
+
+    fn select_syntax_highlight(&mut self) {
+        self.syntax = self.filename.as_deref().and_then(syntax::select);
+        self.invalidate(0);
+    }
+
+    /*** row operations ***/
+
+    /// Byte range of row `at` in the text, without its line break.
+    /// A `\r` before the `\n` belongs to the line break, so DOS files keep theirs.
+    fn row_range(&self, at: usize) -> Range<usize> {
+        let start = self.text.line_to_byte(at);
+        let mut end = self.text.line_to_byte(at + 1).saturating_sub(1).max(start);
+        if end > start && self.text.byte(end - 1) == Some(b'\r') {
+            end -= 1;
+        }
+        start..end
+    }
+
+    /// Byte offset of column `at` of row `at_row`, or of the end of the row if `at` is out of bounds.
+    fn offset(&self, at_row: usize, at: usize) -> usize {
+        let range = self.row_range(at_row);
+        range.start + at.min(range.len())
+    }
+
+    pub fn insert_row(&mut self, at: usize, s: &[u8]) {
+        if at > self.numrows() {
+            return;
+        }
+        let mut line = s.to_vec();
+        line.push(b'\n');
+        let start = self.text.line_to_byte(at);
+        self.replace(start..start, &line);
+    }
+
+    pub fn del_row(&mut self, at: usize) {
+        if at >= self.numrows() {
+            return;
+        }
+        self.replace(self.text.line_to_byte(at)..self.text.line_to_byte(at + 1), b"");
+    }
+
+    /// Inserts `s` into row `at_row` at byte `at`, or at the end of the row if `at` is out of bounds.
+    pub fn row_insert_str(&mut self, at_row: usize, at: usize, s: &[u8]) {
+        let offset = self.offset(at_row, at);
+        self.replace(offset..offset, s);
+    }
+
+    pub fn row_append_string(&mut self, at_row: usize, s: &[u8]) {
+        self.row_insert_str(at_row, usize::MAX, s);
+    }
+
+    /// Deletes the grapheme cluster at byte `at` of row `at_row`, however many bytes it takes.
+    pub fn row_del_char(&mut self, at_row: usize, at: usize) {
+        let end = match self.plain_row(at_row) {
+            Some(row) if at < row.len() => row.next_boundary(at),
+            _ => return,
+        };
+        let offset = self.offset(at_row, at);
+        self.replace(offset..offset + (end - at), b"");
+    }
+
+    /// Inserts `text`, which may span several lines, at byte `at` of row `at_row` as one edit.
+    /// Returns the position right after the inserted text as `(cx, cy)`.
+    pub fn insert_text(&mut self, at_row: usize, at: usize, text: &[u8]) -> (usize, usize) {
+        let at = at.min(self.row_len(at_row));
+        if at_row == self.numrows() {
+            let end = self.text.len();
+            let mut line = text.to_vec();
+            if !line.ends_with(b"\n") {
+                line.push(b'\n');
+            }
+            self.replace(end..end, &line);
+        } else {
+            let offset = self.offset(at_row, at);
+            self.replace(offset..offset, text);
+        }
+
+        match text.iter().rposition(|&c| c == b'\n') {
+            Some(last) => {
+                let lines = text.iter().filter(|&&c| c == b'\n').count();
+                (text.len() - last - 1, at_row + lines)
+            }
+            None => (at + text.len(), at_row),
+        }
+    }
+
+    /// Cuts row `at_row` at byte `at`, moving everything after it into a new row below.
+    pub fn split_row(&mut self, at_row: usize, at: usize) {
+        self.row_insert_str(at_row, at, b"\n");
+    }
+
+    /// Appends row `at_row + 1` to row `at_row` by removing the line break between them.
+    pub fn join_rows(&mut self, at_row: usize) {
+        if at_row + 1 >= self.numrows() {
+            return;
+        }
+        let line_break = self.row_range(at_row).end..self.text.line_to_byte(at_row + 1);
+        self.replace(line_break, b"");
+    }
+
+    /// Returns the text from `start` up to `end`, which may be on different rows.
+    pub fn text_between(&self, start: Cursor, end: Cursor) -> Vec<u8> {
+        self.text.slice(self.position(start)..self.position(end).max(self.position(start)))
+    }
+
+    /// Deletes the text from `start` up to `end`, which may be on different rows.
+    pub fn delete_region(&mut self, start: Cursor, end: Cursor) {
+        let (from, mut to) = (self.position(start), self.position(end));
+        // Running off the end of the text from the middle of a row would take the last line break with it.
+        if to == self.text.len() && start.cx > 0 && self.numrows() > 0 {
+            to = self.row_range(self.numrows() - 1).end.max(from);
+        }
+        if from < to {
+            self.replace(from..to, b"");
+        }
+    }
+
+    /// Indents row `at` with a tab, unless it is empty. Returns how many bytes went in front of it.
+    pub fn indent_row(&mut self, at: usize) -> usize {
+        if self.row_len(at) == 0 {
+            return 0;
+        }
+        self.row_insert_str(at, 0, b"\t");
+        1
+    }
+
+    /// Takes a level of indentation off row `at`: a tab, or up to `TAB_STOP` spaces.
+    /// Returns how many bytes it took.
+    pub fn outdent_row(&mut self, at: usize) -> usize {
+        let len = match self.plain_row(at) {
+            Some(row) if row.chars.first() == Some(&b'\t') => 1,
+            Some(row) => row.chars.iter().take(TAB_STOP).take_while(|&&c| c == b' ').count(),
+            None => 0,
+        };
+        if len > 0 {
+            let start = self.offset(at, 0);
+            self.replace(start..start + len, b"");
+        }
+        len
+    }
+
+    /// Byte offset of `at` in the text, or the end of the text past the last row.
+    pub(crate) fn position(&self, at: Cursor) -> usize {
+        if at.cy >= self.numrows() {
+            self.text.len()
+        } else {
+            self.offset(at.cy, at.cx)
+        }
+    }
+
+    /// The opposite of [`Buffer::position`]: where byte `at` of the text is, as a cursor.
+    pub(crate) fn cursor_at(&self, at: usize) -> Cursor {
+        let cy = self.text.byte_to_line(at.min(self.text.len()));
+        if cy >= self.numrows() {
+            return Cursor::new(0, self.numrows());
+        }
+        let cx = (at - self.text.line_to_byte(cy)).min(self.row_len(cy));
+        Cursor::new(cx, cy)
+    }
+
+    /// Replaces the bytes in `range` of the text with `bytes` and records it in the history.
+    fn replace(&mut self, range: Range<usize>, bytes: &[u8]) {
+        let deleted = self.text.slice(range.clone());
+        self.text.delete(range.clone());
+        self.text.insert(range.start, bytes);
+        self.invalidate(self.text.byte_to_line(range.start));
+        self.changes += 1;
+        self.history.record(Edit {
+            at: range.start,
+            deleted,
+            inserted: bytes.to_vec(),
+        });
+    }
+
+    /*** undo ***/
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Closes the edits made since the last call into an undo step, made by a command of `kind`
+    /// that moved the cursor from `before` to `after`. Runs of typing or deleting join the step before.
+    pub fn commit_edit(&mut self, kind: EditKind, before: Cursor, after: Cursor) {
+        self.history.commit(kind, before, after);
+    }
+
+    /// Reverts the last step and returns where the cursor was before it, or `None` if there is nothing to undo.
+    pub fn undo(&mut self) -> Option<Cursor> {
+        self.commit_stray_edits();
+        let target = self.history.undo_target()?;
+        self.go_to(target)
+    }
+
+    /// Applies the last undone step again and returns where the cursor was after it,
+    /// or `None` if there is nothing to redo. Where the history branches, this follows the branch
+    /// last undone or picked with [`Buffer::next_branch`].
+    pub fn redo(&mut self) -> Option<Cursor> {
+        self.commit_stray_edits();
+        let target = self.history.redo_target()?;
+        self.go_to(target)
+    }
+
+    /// Makes redo follow the next branch leaving the current state.
+    /// Returns which one it follows now and how many there are, or `None` if there is no choice.
+    pub fn next_branch(&mut self) -> Option<(usize, usize)> {
+        self.commit_stray_edits();
+        self.history.next_branch()
+    }
+
+    /// Goes back or forward in the history as far as `travel` says, across branches,
+    /// and returns where to put the cursor, or `None` if that is where the buffer already is.
+    pub fn travel(&mut self, travel: Travel) -> Option<Cursor> {
+        self.commit_stray_edits();
+        let target = self.history.travel_target(travel);
+        self.go_to(target)
+    }
+
+    /// Brings the text to history state `target`, reverting and applying steps on the way.
+    /// Returns the cursor of the last step: where it was before one reverted, after one applied.
+    fn go_to(&mut self, target: usize) -> Option<Cursor> {
+        if target == self.history.state() {
+            return None;
+        }
+        let (mut cursor, mut first) = (Cursor::default(), usize::MAX);
+        for step in self.history.go_to(target) {
+            match step {
+                Move::Revert(node) => {
+                    let step = self.history.step(node);
+                    for edit in step.edits.iter().rev() {
+                        self.text.delete(edit.at..edit.at + edit.inserted.len());
+                        self.text.insert(edit.at, &edit.deleted);
+                        first = first.min(edit.at);
+                    }
+                    cursor = step.before;
+                }
+                Move::Apply(node) => {
+                    let step = self.history.step(node);
+                    for edit in &step.edits {
+                        self.text.delete(edit.at..edit.at + edit.deleted.len());
+                        self.text.insert(edit.at, &edit.inserted);
+                        first = first.min(edit.at);
+                    }
+                    cursor = step.after;
+                }
+            }
+        }
+        self.invalidate(self.text.byte_to_line(first.min(self.text.len())));
+        self.changes += 1;
+        Some(cursor)
+    }
+
+    /// Commits edits made through the row operations without a [`Buffer::commit_edit`] as a step of their own.
+    fn commit_stray_edits(&mut self) {
+        if self.history.has_pending() {
+            self.history.commit(EditKind::Other, Cursor::default(), Cursor::default());
+        }
+    }
+
+    /*** file i/o ***/
+
+    pub fn rows_to_string(&self) -> Vec<u8> {
+        self.text.to_bytes()
+    }
+
+    /// Writes the buffer to its file and returns the number of bytes written. Waits for the rest
+    /// of the file to load first if it hasn't yet.
+    ///
+    /// A mapped file is still read from as it is saved, so it is replaced by a new file with the
+    /// same permissions, and owner if the new file can be given it, rather than written over: the
+    /// file a symlink points to is replaced and the symlink stays, but other hard links to the
+    /// file keep the old text, and ACLs and extended attributes aren't copied.
+    pub fn save(&mut self) -> io::Result<usize> {
+        if self.text.is_loading() {
+            self.text.finish_loading();
+            self.end_with_newline();
+        }
+        let filename = match &self.filename {
+            Some(filename) => filename,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "no file name")),
+        };
+        let len = self.text.len();
+
+        if self.text.is_mapped() {
+            // The text is still read from the old file, so writing over it would pull the rug
+            // from under us. Write a new file next to it and move that over the old one instead.
+            let target = fs::canonicalize(filename)?;
+            let mut tmp = target.clone().into_os_string();
+            tmp.push(".tiny-save");
+            let written = self.write_replacement(&target, Path::new(&tmp));
+            if written.is_err() {
+                let _ = fs::remove_file(&tmp);
+            }
+            written?;
+        } else {
+            // Truncate to the new length ourselves instead of with O_TRUNC,
+            // so a failed write doesn't leave an empty file behind.
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(filename)?;
+            file.set_len(len as u64)?;
+            self.write_text(file)?;
+        }
+
+        self.commit_stray_edits();
+        self.history.seal(); // so the saved state stays where it is
+        self.saved = self.history.state();
+        Ok(len)
+    }
+
+    /// Writes the undo history to the data directory along with a hash of the text,
+    /// so [`Buffer::load_history`] can pick it up in a later session. Call it right after saving.
+    pub fn save_history(&self) -> io::Result<()> {
+        let (filename, path) = match self.history_file() {
+            Some(file) => file,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "no data directory")),
+        };
+        let hash = history::content_hash(self.text.chunks());
+        data::write(&path, &self.history.encode(&filename, hash))
+    }
+
+    /// Reads back the undo history saved with the file, if the file hasn't changed since.
+    /// Returns whether there was one. Waits for the rest of the file to load first, to hash it,
+    /// if there is a history to check it against.
+    pub fn load_history(&mut self) -> io::Result<bool> {
+        let (filename, path) = match self.history_file() {
+            Some(file) if file.1.exists() => file,
+            _ => return Ok(false),
+        };
+        let encoded = fs::read(path)?;
+        // The whole file is needed to hash it.
+        self.text.finish_loading();
+        self.end_with_newline();
+        let hash = history::content_hash(self.text.chunks());
+        match History::decode(&encoded, &filename, hash) {
+            Some(history) => {
+                self.history = history;
+                self.saved = self.history.state();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the absolute path of the file and where its undo history goes.
+    fn history_file(&self) -> Option<(String, PathBuf)> {
+        let absolute = fs::canonicalize(self.filename.as_deref()?).ok()?;
+        let path = data::undo_file(&absolute)?;
+        Some((absolute.to_string_lossy().into_owned(), path))
+    }
+
+    /// Writes the text to `tmp` with the permissions and owner of `target`, and moves it over `target`.
+    fn write_replacement(&self, target: &Path, tmp: &Path) -> io::Result<()> {
+        let metadata = fs::metadata(target)?;
+        let file = File::create(tmp)?;
+        file.set_permissions(metadata.permissions())?;
+        // Only root can give a file away, but anyone can keep the group of their own file.
+        let _ = unix::fs::fchown(&file, Some(metadata.uid()), Some(metadata.gid()));
+        self.write_text(file)?;
+        fs::rename(tmp, target)
+    }
+
+    fn write_text(&self, file: File) -> io::Result<()> {
+        let mut writer = BufWriter::new(file);
+        for chunk in self.text.chunks() {
+            writer.write_all(chunk)?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    /// Returns a path in the temporary directory that no other test uses.
+    fn temp_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("tiny-editor-buffer-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    /// Returns numbered lines enough to be mapped, the last one without a newline.
+    fn big_contents() -> Vec<u8> {
+        let lines = MMAP_MIN_LEN as usize / 8 + 1000;
+        let mut contents: Vec<u8> = (0..lines).flat_map(|i| format!("{:07}\n", i).into_bytes()).collect();
+        contents.pop();
+        contents
+    }
+
+    fn finish(buffer: &mut Buffer) {
+        while buffer.is_loading() {
+            if !buffer.poll_loading() {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+        }
+    }
+
+    #[test]
+    fn big_files_load_a_part_at_a_time() {
+        let path = temp_file("big.txt");
+        let contents = big_contents();
+        fs::write(&path, &contents).unwrap();
+        let mut buffer = Buffer::open(path.to_str().unwrap()).unwrap();
+        assert!(buffer.text().is_mapped());
+        assert!(buffer.is_loading());
+        let loaded = buffer.numrows();
+        assert!(loaded > 0 && loaded < contents.len() / 8);
+        assert_eq!(buffer.row_chars(loaded - 1).unwrap(), format!("{:07}", loaded - 1).as_bytes());
+
+        finish(&mut buffer);
+        assert_eq!(buffer.numrows(), contents.len() / 8 + 1);
+        assert_eq!(buffer.text().len(), contents.len() + 1); // with a newline at the end
+        assert_eq!(buffer.changes(), 0);
+        assert!(!buffer.is_dirty());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saving_a_big_file_replaces_it() {
+        let path = temp_file("saved.txt");
+        let link = temp_file("link.txt");
+        fs::write(&path, big_contents()).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+
+        let mut buffer = Buffer::open(link.to_str().unwrap()).unwrap();
+        buffer.row_insert_str(0, 0, b"new ");
+        let len = buffer.save().unwrap();
+        let saved = fs::read(&path).unwrap();
+        assert_eq!(saved.len(), len);
+        assert!(saved.starts_with(b"new 0000000\n0000001\n") && saved.ends_with(b"\n"));
+        // The symlink is still there and leads to the new file, with the permissions of the old one.
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tiny-save");
+        assert!(!Path::new(&tmp).exists());
+        // Edited as it loaded, the newline given to the last row on saving is undone with the edit.
+        buffer.undo();
+        assert_eq!(buffer.rows_to_string(), big_contents());
+        fs::remove_file(&link).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn undoing_back_to_where_the_file_was_saved() {
+        let path = temp_file("undo.txt");
+        let mut buffer = Buffer::from_bytes(b"abc");
+        buffer.set_filename(path.to_str().unwrap());
+        assert_eq!(buffer.rows_to_string(), b"abc\n");
+        assert!(!buffer.is_dirty());
+
+        buffer.row_insert_str(0, 3, b"d");
+        buffer.commit_edit(EditKind::Insert, Cursor::new(3, 0), Cursor::new(4, 0));
+        buffer.save().unwrap();
+        buffer.row_insert_str(0, 4, b"e");
+        buffer.commit_edit(EditKind::Insert, Cursor::new(4, 0), Cursor::new(5, 0));
+        assert!(buffer.is_dirty());
+        assert_eq!(buffer.undo(), Some(Cursor::new(4, 0)));
+        assert!(!buffer.is_dirty());
+        buffer.undo();
+        assert!(buffer.is_dirty());
+        // The newline the file was given on opening is where the history starts.
+        assert_eq!((buffer.rows_to_string(), buffer.undo()), (b"abc\n".to_vec(), None));
+        buffer.redo();
+        assert!(!buffer.is_dirty());
+        fs::remove_file(&path).unwrap();
+    }
+}
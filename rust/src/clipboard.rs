@@ -0,0 +1,186 @@
+//! The system clipboard, for text to go back and forth between the editor and other programs.
+//!
+//! Copying goes out through the terminal with OSC 52 (`ESC ] 52 ; c ; <base64> BEL`), which
+//! terminals that support it hand to the clipboard of the machine they run on, even over SSH.
+//! On top of that, or instead, helper commands like `xclip`, `wl-copy`/`wl-paste` or
+//! `pbcopy`/`pbpaste` can be set to copy to and paste from. Without a paste command, pasting
+//! only sees what was cut or copied in the editor, from its [`KillRing`](crate::KillRing).
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Clone, Debug)]
+pub struct Clipboard {
+    pub osc52: bool, // copy through the terminal
+    pub copy_command: Option<String>, // run with `sh -c`, gets the text on stdin
+    pub paste_command: Option<String>, // run with `sh -c`, prints the text on stdout
+}
+
+impl Default for Clipboard {
+    fn default() -> Clipboard {
+        Clipboard {
+            osc52: true,
+            copy_command: None,
+            paste_command: None,
+        }
+    }
+}
+
+impl Clipboard {
+    /// Runs the copy command, if there is one, with `text` on its standard input.
+    pub fn copy(&self, text: &[u8]) -> io::Result<()> {
+        let command = match &self.copy_command {
+            Some(command) => command,
+            None => return Ok(()),
+        };
+        // Output would land in the middle of the screen, and the terminal is ours.
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let written = match child.stdin.take() {
+            Some(mut stdin) => stdin.write_all(text),
+            None => Ok(()),
+        };
+        // Even a command that stopped reading is waited for, or it would linger as a zombie.
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("`{}` {}", command, status)));
+        }
+        written
+    }
+
+    /// Runs the paste command and returns what it printed, or `None` if there is no paste command.
+    pub fn paste(&self) -> Option<io::Result<Vec<u8>>> {
+        let command = self.paste_command.as_ref()?;
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        Some(match output {
+            Ok(output) if output.status.success() => Ok(output.stdout),
+            Ok(output) => Err(io::Error::other(format!("`{}` {}", command, output.status))),
+            Err(err) => Err(err),
+        })
+    }
+}
+
+/// Returns the OSC 52 sequence that asks the terminal to put `text` on the clipboard.
+pub fn osc52(text: &[u8]) -> Vec<u8> {
+    let mut seq = b"\x1b]52;c;".to_vec();
+    seq.extend_from_slice(base64_encode(text).as_bytes());
+    seq.push(b'\x07');
+    seq
+}
+
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes what [`base64_encode`] wrote. Returns `None` if `s` isn't base64.
+pub(crate) fn base64_decode(s: &[u8]) -> Option<Vec<u8>> {
+    let s = s.strip_suffix(b"==").or_else(|| s.strip_suffix(b"=")).unwrap_or(s);
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.chunks(4) {
+        if chunk.len() == 1 {
+            return None; // six bits, not enough for a byte
+        }
+        let mut n = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let digit = BASE64.iter().position(|&d| d == c)? as u32;
+            n |= digit << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn base64() {
+        for (text, encoded) in [("", ""), ("M", "TQ=="), ("Ma", "TWE="), ("Man", "TWFu"), ("hello\n", "aGVsbG8K")] {
+            assert_eq!(base64_encode(text.as_bytes()), encoded);
+            assert_eq!(base64_decode(encoded.as_bytes()).as_deref(), Some(text.as_bytes()));
+        }
+        let bytes: Vec<u8> = (0..=255).collect();
+        for len in 0..bytes.len() {
+            let encoded = base64_encode(&bytes[..len]);
+            assert_eq!(base64_decode(encoded.as_bytes()).as_deref(), Some(&bytes[..len]), "{} bytes", len);
+        }
+        assert_eq!(base64_decode(b"TW*u"), None);
+        assert_eq!(base64_decode(b"T"), None);
+    }
+
+    #[test]
+    fn osc52_sequence() {
+        assert_eq!(osc52(b"Man"), b"\x1b]52;c;TWFu\x07");
+    }
+
+    #[test]
+    fn copy_and_paste_through_helper_commands() {
+        let path = std::env::temp_dir().join(format!("tiny-editor-{}-clipboard", std::process::id()));
+        let clipboard = Clipboard {
+            copy_command: Some(format!("cat > '{}'", path.display())),
+            paste_command: Some(format!("cat '{}'", path.display())),
+            ..Clipboard::default()
+        };
+        clipboard.copy("héllo\nworld".as_bytes()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "héllo\nworld");
+        assert_eq!(clipboard.paste().unwrap().unwrap(), "héllo\nworld".as_bytes());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn failing_or_missing_helper_commands() {
+        let failing = Clipboard {
+            copy_command: Some("exit 3".to_string()),
+            paste_command: Some("echo partial; exit 1".to_string()),
+            ..Clipboard::default()
+        };
+        assert!(failing.copy(b"text").is_err());
+        assert!(failing.paste().unwrap().is_err());
+
+        let none = Clipboard::default();
+        assert!(none.copy(b"text").is_ok());
+        assert!(none.paste().is_none());
+    }
+
+    #[test]
+    fn copy_commands_that_stop_reading() {
+        // The command closes its input before it has all of it, and finishes a while later.
+        let path = std::env::temp_dir().join(format!("tiny-editor-{}-stopped-reading", std::process::id()));
+        let clipboard = Clipboard {
+            copy_command: Some(format!("exec 0<&-; sleep 0.2; echo done > '{}'", path.display())),
+            ..Clipboard::default()
+        };
+        let err = clipboard.copy(&vec![b'x'; 1 << 20]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "done\n", "copy returned before the command finished");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+//! Cursor position and movement over a [`Buffer`].
+
+use std::cmp::Ordering;
+
+use crate::buffer::Buffer;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// A position in the buffer. `cx` is a byte index into the row's `chars`, always at the start
+/// of a grapheme cluster, and `cy` may be one past the last row, where typing appends a new row.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cursor {
+    pub cx: usize, // cursor x position
+    pub cy: usize, // cursor y position
+}
+
+/// Cursors are ordered by their position in the buffer: by row first, then by column.
+impl Ord for Cursor {
+    fn cmp(&self, other: &Cursor) -> Ordering {
+        (self.cy, self.cx).cmp(&(other.cy, other.cx))
+    }
+}
+
+impl PartialOrd for Cursor {
+    fn partial_cmp(&self, other: &Cursor) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Cursor {
+    pub fn new(cx: usize, cy: usize) -> Cursor {
+        Cursor { cx, cy }
+    }
+
+    /// Moves one grapheme cluster left or right, or one row up or down.
+    /// Up and down keep the cursor in the same screen column where the new row is long enough.
+    pub fn move_to(&mut self, direction: Direction, buffer: &Buffer) {
+        let row = buffer.plain_row(self.cy);
+        match direction {
+            Direction::Left => {
+                if self.cx != 0 {
+                    self.cx = row.map_or(0, |row| row.prev_boundary(self.cx));
+                } else if self.cy > 0 {
+                    self.cy -= 1;
+                    self.cx = buffer.row_len(self.cy);
+                }
+            }
+            Direction::Right => match row {
+                Some(row) if self.cx < row.len() => self.cx = row.next_boundary(self.cx),
+                Some(_) => {
+                    self.cy += 1;
+                    self.cx = 0;
+                }
+                None => {}
+            },
+            Direction::Up | Direction::Down => {
+                let rx = row.map_or(0, |row| row.cx_to_rx(self.cx));
+                match direction {
+                    Direction::Up => self.cy = self.cy.saturating_sub(1),
+                    _ => self.cy = (self.cy + 1).min(buffer.numrows()),
+                }
+                self.cx = buffer.plain_row(self.cy).map_or(0, |row| row.rx_to_cx(rx));
+            }
+        }
+
+        self.clamp_to(buffer);
+    }
+
+    /// Snaps `cx` back to the end of the row if the row is shorter than it,
+    /// and to the start of the grapheme cluster it points into.
+    pub fn clamp_to(&mut self, buffer: &Buffer) {
+        self.cy = self.cy.min(buffer.numrows());
+        self.cx = buffer.plain_row(self.cy).map_or(0, |row| row.floor_boundary(self.cx));
+    }
+
+    pub fn home(&mut self) {
+        self.cx = 0;
+    }
+
+    pub fn end(&mut self, buffer: &Buffer) {
+        self.cx = buffer.row_len(self.cy);
+    }
+}
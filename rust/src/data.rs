@@ -0,0 +1,34 @@
+//! Files tiny-editor keeps for itself between sessions, under `$XDG_DATA_HOME/tiny-editor`
+//! (`~/.local/share/tiny-editor` if that isn't set).
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn data_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("tiny-editor"))
+}
+
+/// Returns the file the undo history of the file at `absolute` is kept in, named after the path
+/// with `/` turned into `%`, the way Vim names the files in its `undodir`.
+pub fn undo_file(absolute: &Path) -> Option<PathBuf> {
+    let name = absolute.to_string_lossy().replace('/', "%");
+    Some(data_dir()?.join("undo").join(name))
+}
+
+/// Returns the file the answers to the prompts are kept in.
+pub fn prompt_history_file() -> Option<PathBuf> {
+    Some(data_dir()?.join("history"))
+}
+
+/// Writes `contents` to `path`, creating the directories on the way.
+pub fn write(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
@@ -0,0 +1,2415 @@
+//! The editor state and keypress handling, the equivalent of `editorConfig` and
+//! `editorProcessKeypress` in the C editor.
+
+use std::io;
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::buffer::Buffer;
+use crate::clipboard::{self, Clipboard};
+use crate::cursor::{Cursor, Direction};
+use crate::data;
+use crate::history::{EditKind, Travel};
+use crate::key::{Event, Key, KeyCode, Modifiers, MouseButton, MouseEvent, MouseKind};
+use crate::kill_ring::{Kill, KillRing};
+use crate::prompt_history::{PromptHistory, PromptKind};
+use crate::row::str_width;
+use crate::search::{is_word_byte, Matcher, SearchOptions};
+use crate::terminal::Terminal;
+use crate::window::{Layout, Rect, Split, Window};
+use crate::QUIT_TIMES;
+
+/// How many rows one notch of the mouse wheel scrolls.
+const MOUSE_SCROLL_ROWS: usize = 3;
+/// Past this many bytes, the matches of the search are only counted once it is confirmed, not
+/// as it is typed or again after every edit.
+const COUNT_MAX_LEN: usize = 8 << 20;
+/// How often the screen is redrawn while a file is loading, to show the rows loaded in the meantime.
+const LOADING_REFRESH_MS: u64 = 100;
+
+type PromptCallback = fn(&mut Editor, &mut Prompt, Key);
+type PromptDone = fn(&mut Editor, Option<String>);
+
+/// A prompt being typed into the message bar.
+struct Prompt {
+    template: String, // shown with `{}` replaced by `buf`
+    buf: String,
+    callback: Option<PromptCallback>, // called after every keypress
+    done: PromptDone, // called with the answer, or `None` if the user pressed Escape
+    finished: bool, // set by `callback` to end the prompt as if the user pressed Enter
+    kind: Option<PromptKind>, // the history Up and Down go through, if any
+    back: Option<usize>, // how many answers back in the history `buf` was brought from
+    typed: String, // what was in `buf` before going back in the history
+}
+
+/// A rectangle of screen columns over a run of rows, for block selection. It is kept in screen
+/// columns rather than in cursors so it can reach past the end of short rows on its way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub anchor: (usize, usize), // row and screen column of the corner the selection started from
+    pub corner: (usize, usize), // row and screen column of the opposite corner
+}
+
+impl Block {
+    pub fn rows(&self) -> RangeInclusive<usize> {
+        self.anchor.0.min(self.corner.0)..=self.anchor.0.max(self.corner.0)
+    }
+
+    /// Returns the screen columns selected, which are none when the block is a column to type into.
+    pub fn columns(&self) -> Range<usize> {
+        self.anchor.1.min(self.corner.1)..self.anchor.1.max(self.corner.1)
+    }
+}
+
+/// Where the last paste went, so it can be swapped for an older entry of the kill ring.
+#[derive(Clone, Copy)]
+struct Yank {
+    start: Cursor,
+    end: Cursor,
+    index: usize, // the entry of the kill ring pasted
+}
+
+/// An open buffer other than the one being edited, with where its view was left.
+struct Parked {
+    buffer: Buffer,
+    cursor: Cursor,
+    rowoff: usize,
+    coloff: usize,
+}
+
+/// Persistent state of `find_callback`, the equivalent of the `static` locals in C, and of a
+/// replace going through the matches.
+#[derive(Default)]
+struct FindState {
+    current: Option<(Cursor, Cursor)>, // start and end of the match shown
+    search: Option<Matcher>, // what was searched for, with every match drawn as `Highlight::Match` until Escape
+    matches: Option<Vec<(Cursor, Cursor)>>, // every match of `search`, in order, or `None` if they weren't counted
+    matched: Option<usize>, // the changes to the buffer `matches` were counted, or given up on, after
+    count_due: bool, // the search was confirmed and its matches are to be counted, however big the text
+    saved_view: (Cursor, usize, usize), // cursor, coloff and rowoff to restore when the search is cancelled
+    matcher: Option<Matcher>, // what is being replaced
+    with: String, // what it is replaced with
+    replaced: usize,
+}
+
+pub struct Editor {
+    pub(crate) buffer: Buffer,
+    buffers: Vec<Option<Parked>>, // every open buffer in order, with `None` for `buffer` itself
+    pub(crate) current: usize, // the index of `buffer` in `buffers`
+    pub(crate) cursor: Cursor,
+    pub(crate) others: Vec<Cursor>, // more cursors that edits apply to as well, in order
+    pub(crate) anchor: Option<Cursor>, // where a mouse drag started, the selection runs from here to the cursor
+    pub(crate) block: Option<Block>, // block selection, instead of `anchor`
+    pub(crate) rx: usize, // render x position
+    pub(crate) rowoff: usize, // row offset
+    pub(crate) coloff: usize, // column offset
+    pub(crate) screenrows: usize, // of the focused window's text
+    pub(crate) screencols: usize,
+    pub(crate) windows: Vec<Window>, // every window, with the view of the focused one in `current`, `cursor`, `rowoff` and `coloff` instead
+    pub(crate) layout: Layout,
+    pub(crate) focused: usize, // the index of the window being edited in `windows`
+    pub(crate) area: Rect, // the screen between the tab line and the message bar, for the windows to share
+    pub(crate) tab_line: bool, // the top row of the screen lists the buffers
+    pub(crate) statusmsg: String, // status message
+    pub(crate) statusmsg_time: Instant, // status message time
+    quit_times: u32,
+    close_confirmed: bool, // Ctrl-W was pressed once on a dirty buffer, and closes it if pressed again
+    persistent_undo: bool, // keep the undo history of files in the data directory between sessions
+    kill_ring: KillRing,
+    clipboard: Clipboard,
+    pub(crate) terminal_out: Vec<u8>, // escape sequences to send the terminal with the next frame
+    yank: Option<Yank>, // set by a paste until the next keypress
+    find: FindState,
+    search_options: SearchOptions,
+    prompt: Option<Prompt>,
+    prompt_history: PromptHistory,
+    prompt_history_file: Option<PathBuf>, // where `prompt_history` is saved whenever it changes
+}
+
+impl Editor {
+    /// Creates an editor for a terminal of `rows` by `cols` cells.
+    pub fn new(rows: usize, cols: usize) -> Editor {
+        Editor {
+            buffer: Buffer::new(),
+            buffers: vec![None],
+            current: 0,
+            cursor: Cursor::default(),
+            others: Vec::new(),
+            anchor: None,
+            block: None,
+            rx: 0,
+            rowoff: 0,
+            coloff: 0,
+            screenrows: rows.saturating_sub(2).max(1), // make room for status bar and message bar, but keep a row of text
+            screencols: cols.max(1),
+            windows: vec![Window::default()],
+            layout: Layout::Window(0),
+            focused: 0,
+            area: Rect { top: 0, left: 0, rows: rows.saturating_sub(1), cols },
+            tab_line: false,
+            statusmsg: String::new(),
+            statusmsg_time: Instant::now(),
+            quit_times: QUIT_TIMES,
+            close_confirmed: false,
+            persistent_undo: false,
+            kill_ring: KillRing::default(),
+            clipboard: Clipboard::default(),
+            terminal_out: Vec::new(),
+            yank: None,
+            find: FindState::default(),
+            search_options: SearchOptions::default(),
+            prompt: None,
+            prompt_history: PromptHistory::default(),
+            prompt_history_file: None,
+        }
+    }
+
+    /// Opens `filename` in a new buffer and switches to it, or to the buffer it is already open in.
+    /// An empty buffer that hasn't been touched is replaced rather than kept around.
+    pub fn open(&mut self, filename: &str) -> io::Result<()> {
+        if let Some(index) = (0..self.buffers.len()).find(|&i| self.buffer_at(i).filename() == Some(filename)) {
+            self.switch_buffer(index);
+            return Ok(());
+        }
+        let buffer = Buffer::open(filename)?;
+        let untouched = self.buffer.filename().is_none() && self.buffer.numrows() == 0 && !self.buffer.is_dirty();
+        if !untouched {
+            self.buffers.push(None);
+            self.switch_buffer(self.buffers.len() - 1);
+        }
+        self.buffer = buffer;
+        (self.cursor, self.rowoff, self.coloff) = (Cursor::default(), 0, 0);
+        self.reset_view_state();
+        self.load_history();
+        Ok(())
+    }
+
+    /// Restores the undo history of the buffer's file with persistent undo on. A file still
+    /// loading is hashed for it once it has, from [`Editor::poll_buffers`], rather than waited for here.
+    fn load_history(&mut self) {
+        if self.persistent_undo && !self.buffer.is_loading() {
+            let loaded = self.buffer.load_history();
+            self.history_loaded(loaded);
+        }
+    }
+
+    fn history_loaded(&mut self, loaded: io::Result<bool>) {
+        match loaded {
+            Ok(true) => self.set_status_message("Undo history restored"),
+            Ok(false) => {}
+            Err(err) => self.set_status_message(&format!("Can't read undo history: {}", err)),
+        }
+    }
+
+    /// Adds the rows loaded since the last call to every buffer whose file is loading, open in a
+    /// window or not, and restores the undo history of those that have finished. Returns whether
+    /// any is still loading.
+    fn poll_buffers(&mut self) -> bool {
+        if self.buffer.poll_loading() && !self.buffer.is_loading() {
+            self.load_history();
+        }
+        let mut loading = self.buffer.is_loading();
+        let mut loaded = Vec::new();
+        for parked in self.buffers.iter_mut().flatten() {
+            let buffer = &mut parked.buffer;
+            if buffer.poll_loading() && !buffer.is_loading() && self.persistent_undo {
+                loaded.push(buffer.load_history());
+            }
+            loading |= buffer.is_loading();
+        }
+        for loaded in loaded {
+            self.history_loaded(loaded);
+        }
+        loading
+    }
+
+    /// Saves the undo history of every file saved from now on, and restores it when the file is
+    /// opened again unchanged.
+    pub fn set_persistent_undo(&mut self, on: bool) {
+        self.persistent_undo = on;
+    }
+
+    /// Reads the answers given to the prompts in earlier sessions from the data directory, and
+    /// saves them there along with the new ones from now on.
+    pub fn load_prompt_history(&mut self) -> io::Result<()> {
+        let path = match data::prompt_history_file() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        self.prompt_history = PromptHistory::load(&path)?;
+        self.prompt_history_file = Some(path);
+        Ok(())
+    }
+
+    /// Shows or hides the tab line, which takes the top row of the screen from the windows.
+    pub fn set_tab_line(&mut self, on: bool) {
+        if on == self.tab_line {
+            return;
+        }
+        self.tab_line = on;
+        let bottom = self.area.top + self.area.rows;
+        self.area.top = if on { 1 } else { 0 };
+        self.area.rows = bottom.saturating_sub(self.area.top);
+        self.fit_to_window();
+    }
+
+    /// Sets how text copied in the editor gets to the system clipboard and back.
+    pub fn set_clipboard(&mut self, clipboard: Clipboard) {
+        self.clipboard = clipboard;
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    pub fn block(&self) -> Option<Block> {
+        self.block
+    }
+
+    /// Returns the buffer at `index` in the buffer list.
+    pub fn buffer_at(&self, index: usize) -> &Buffer {
+        match &self.buffers[index] {
+            Some(parked) => &parked.buffer,
+            None => &self.buffer,
+        }
+    }
+
+    /// Returns how many buffers are open.
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Returns the index of the buffer being edited in the buffer list.
+    pub fn current_buffer(&self) -> usize {
+        self.current
+    }
+
+    /// Returns every cursor, the main one included, in order.
+    pub fn cursors(&self) -> Vec<Cursor> {
+        let mut cursors = self.others.clone();
+        cursors.push(self.cursor);
+        cursors.sort();
+        cursors
+    }
+
+    /// Returns the selected region as `(start, end)`, with `end` excluded.
+    pub fn selection(&self) -> Option<(Cursor, Cursor)> {
+        let anchor = self.anchor?;
+        match anchor.cmp(&self.cursor) {
+            std::cmp::Ordering::Less => Some((anchor, self.cursor)),
+            std::cmp::Ordering::Greater => Some((self.cursor, anchor)),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    pub fn status_message(&self) -> &str {
+        &self.statusmsg
+    }
+
+    pub fn set_status_message(&mut self, msg: &str) {
+        self.statusmsg = msg.to_string();
+        self.statusmsg_time = Instant::now();
+    }
+
+    /*** editor operations ***/
+
+    /// Whether the buffer can't be edited yet because its file is still loading. Says so if it can't.
+    fn is_read_only(&mut self) -> bool {
+        if self.buffer.is_loading() {
+            self.set_status_message("Read-only until the file has finished loading");
+        }
+        self.buffer.is_loading()
+    }
+
+    fn insert_char(&mut self, c: char) {
+        if self.is_read_only() {
+            return;
+        }
+        if self.cursor.cy == self.buffer.numrows() {
+            self.buffer.insert_row(self.buffer.numrows(), b""); // append empty row at the end of the file
+        }
+        let mut buf = [0; 4];
+        let encoded = c.encode_utf8(&mut buf).as_bytes();
+        self.buffer.row_insert_str(self.cursor.cy, self.cursor.cx, encoded);
+        self.cursor.cx += encoded.len();
+    }
+
+    /// Inserts `text` at the cursor as one edit and leaves the cursor after it.
+    fn insert_text(&mut self, text: &[u8]) {
+        if self.is_read_only() {
+            return;
+        }
+        let Cursor { cx, cy } = self.cursor;
+        let (cx, cy) = self.buffer.insert_text(cy, cx, text);
+        self.cursor = Cursor::new(cx, cy);
+    }
+
+    fn insert_newline(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        if self.cursor.cx == 0 {
+            self.buffer.insert_row(self.cursor.cy, b"");
+        } else {
+            self.buffer.split_row(self.cursor.cy, self.cursor.cx);
+        }
+        self.cursor.cy += 1;
+        self.cursor.cx = 0;
+    }
+
+    fn del_char(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        let Cursor { cx, cy } = self.cursor;
+        if cy == self.buffer.numrows() {
+            return; // cursor is past the end of the file
+        }
+        if cx == 0 && cy == 0 {
+            return; // cursor is at the beginning of the file
+        }
+
+        if cx > 0 {
+            let prev = self.buffer.plain_row(cy).map_or(0, |row| row.prev_boundary(cx));
+            self.buffer.row_del_char(cy, prev); // delete char to the left of the cursor
+            self.cursor.cx = prev;
+        } else {
+            self.cursor.cx = self.buffer.row_len(cy - 1); // move cursor to the end of the previous line
+            self.buffer.join_rows(cy - 1); // append current line to previous line
+            self.cursor.cy -= 1;
+        }
+    }
+
+    /*** selection ***/
+
+    /// Deletes the text from `start` up to `end` and leaves the cursor where it started.
+    fn delete_region(&mut self, (start, end): (Cursor, Cursor)) {
+        if self.is_read_only() {
+            return;
+        }
+        self.buffer.delete_region(start, end);
+        self.cursor = start;
+        self.cursor.clamp_to(&self.buffer);
+    }
+
+    /// Indents, or outdents, every row the selection touches, or the cursor's row without one.
+    /// The selection stays on the same text.
+    fn indent(&mut self, outdent: bool) {
+        if self.is_read_only() {
+            return;
+        }
+        let (start, end) = self.selection().unwrap_or((self.cursor, self.cursor));
+        // A selection that ends at the start of a row doesn't take anything from it.
+        let last = if end.cy > start.cy && end.cx == 0 { end.cy - 1 } else { end.cy };
+        for at in start.cy..=last {
+            let delta = if outdent { self.buffer.outdent_row(at) } else { self.buffer.indent_row(at) };
+            for cursor in [Some(&mut self.cursor), self.anchor.as_mut()].into_iter().flatten() {
+                if cursor.cy == at && outdent {
+                    cursor.cx = cursor.cx.saturating_sub(delta);
+                } else if cursor.cy == at && cursor.cx > 0 {
+                    cursor.cx += delta;
+                }
+            }
+        }
+    }
+
+    /*** buffers ***/
+
+    /// Makes buffer `index` the one being edited, with the cursor and scroll it was left with.
+    /// A new slot pushed to the list gets an empty view, to be filled by the caller.
+    pub fn switch_buffer(&mut self, index: usize) {
+        if index == self.current || index >= self.buffers.len() {
+            return;
+        }
+        let parked = self.buffers[index].take().unwrap_or_else(|| Parked {
+            buffer: Buffer::new(),
+            cursor: Cursor::default(),
+            rowoff: 0,
+            coloff: 0,
+        });
+        let left = Parked {
+            buffer: std::mem::replace(&mut self.buffer, parked.buffer),
+            cursor: self.cursor,
+            rowoff: self.rowoff,
+            coloff: self.coloff,
+        };
+        self.buffers[self.current] = Some(left);
+        self.current = index;
+        (self.cursor, self.rowoff, self.coloff) = (parked.cursor, parked.rowoff, parked.coloff);
+        self.reset_view_state();
+    }
+
+    /// Forgets what belongs to the view of one buffer and makes no sense in another.
+    fn reset_view_state(&mut self) {
+        self.others.clear();
+        self.anchor = None;
+        self.block = None;
+        self.yank = None;
+        self.find = FindState::default();
+    }
+
+    /// Closes the buffer being edited and switches to the one after it, or before it if it was the last.
+    /// Closing the only buffer leaves an empty one.
+    fn close_buffer(&mut self) {
+        if self.buffer.is_dirty() && !self.close_confirmed {
+            self.set_status_message("WARNING!!! File has unsaved changes. Press Ctrl-W again to close it.");
+            self.close_confirmed = true;
+            return;
+        }
+        self.close_confirmed = false;
+        let closed = self.current;
+        if self.buffers.len() == 1 {
+            self.buffer = Buffer::new();
+            (self.cursor, self.rowoff, self.coloff) = (Cursor::default(), 0, 0);
+            self.reset_view_state();
+        } else {
+            self.switch_buffer(if closed + 1 < self.buffers.len() { closed + 1 } else { closed - 1 });
+            self.buffers.remove(closed);
+            if self.current > closed {
+                self.current -= 1;
+            }
+        }
+        // Other windows on the closed buffer show the one the focused window went to instead.
+        for (index, window) in self.windows.iter_mut().enumerate() {
+            if index == self.focused {
+                continue;
+            }
+            if window.buffer == closed {
+                *window = Window { buffer: self.current, ..Window::default() };
+            } else if window.buffer > closed {
+                window.buffer -= 1;
+            }
+        }
+        self.set_status_message("");
+    }
+
+    /// Returns how many open buffers have unsaved changes.
+    fn dirty_buffers(&self) -> usize {
+        (0..self.buffers.len()).filter(|&i| self.buffer_at(i).is_dirty()).count()
+    }
+
+    /// Returns the name of buffer `index` as it is shown in lists, with `*` if it has unsaved changes.
+    pub fn buffer_name(&self, index: usize) -> String {
+        let buffer = self.buffer_at(index);
+        let name = buffer.filename().unwrap_or("[No Name]");
+        format!("{}{}", name, if buffer.is_dirty() { "*" } else { "" })
+    }
+
+    fn list_buffers(&mut self) {
+        let list: Vec<String> = (0..self.buffers.len())
+            .map(|i| match i == self.current {
+                true => format!("[{} {}]", i + 1, self.buffer_name(i)),
+                false => format!("{} {}", i + 1, self.buffer_name(i)),
+            })
+            .collect();
+        self.prompt(&format!("{} | Switch to: {{}}", list.join(" ")), Some(PromptKind::Command), None, Editor::list_buffers_done);
+    }
+
+    /// Switches to the buffer with the number typed, or else the first whose file name contains what was typed.
+    fn list_buffers_done(&mut self, answer: Option<String>) {
+        let answer = match answer {
+            Some(answer) => answer,
+            None => return,
+        };
+        let index = match answer.trim().parse::<usize>() {
+            Ok(n) if (1..=self.buffers.len()).contains(&n) => Some(n - 1),
+            _ => (0..self.buffers.len())
+                .find(|&i| self.buffer_at(i).filename().is_some_and(|name| name.contains(answer.trim()))),
+        };
+        match index {
+            Some(index) => self.switch_buffer(index),
+            None => self.set_status_message(&format!("No buffer matches \"{}\"", answer)),
+        }
+    }
+
+    /// Returns the tabs that fit on the tab line, as the buffer, its label and the screen columns
+    /// it takes, starting late enough for the current buffer to be among them.
+    pub(crate) fn tabs(&self) -> Vec<(usize, String, Range<usize>)> {
+        let labels: Vec<String> = (0..self.buffers.len())
+            .map(|i| {
+                let buffer = self.buffer_at(i);
+                let name = buffer.filename().map(Path::new).and_then(Path::file_name);
+                let name = name.map_or("[No Name]".into(), |name| name.to_string_lossy());
+                format!(" {} {}{} ", i + 1, name, if buffer.is_dirty() { "*" } else { "" })
+            })
+            .collect();
+        let cols = self.area.cols;
+        let mut first = 0;
+        while first < self.current && labels[first..=self.current].iter().map(|l| str_width(l)).sum::<usize>() > cols {
+            first += 1;
+        }
+        let mut tabs = Vec::new();
+        let mut left = 0;
+        for (i, label) in labels.into_iter().enumerate().skip(first) {
+            if left >= cols {
+                break;
+            }
+            let width = str_width(&label);
+            tabs.push((i, label, left..(left + width).min(cols)));
+            left += width;
+        }
+        tabs
+    }
+
+    fn open_prompt(&mut self) {
+        self.prompt("Open: {} (ESC to cancel)", Some(PromptKind::File), None, Editor::open_done);
+    }
+
+    fn open_done(&mut self, filename: Option<String>) {
+        if let Some(filename) = filename {
+            if let Err(err) = self.open(&filename) {
+                self.set_status_message(&format!("Can't open {}: {}", filename, err));
+            }
+        }
+    }
+
+    /*** windows ***/
+
+    /// Returns what window `index` shows, the focused one included.
+    pub fn window(&self, index: usize) -> Window {
+        match index == self.focused {
+            true => Window { buffer: self.current, cursor: self.cursor, rowoff: self.rowoff, coloff: self.coloff },
+            false => self.windows[index],
+        }
+    }
+
+    /// Returns the part of the screen window `index` takes, its status bar included.
+    pub fn window_rect(&self, index: usize) -> Rect {
+        let windows = self.layout.windows(self.area);
+        windows.into_iter().find(|&(window, _)| window == index).map_or(self.area, |(_, rect)| rect)
+    }
+
+    /// Returns how many windows the screen is split into.
+    pub fn window_count(&self) -> usize {
+        self.windows.len()
+    }
+
+    /// Returns the index of the window being edited.
+    pub fn focused_window(&self) -> usize {
+        self.focused
+    }
+
+    /// Makes window `index` the one being edited, leaving the view of the focused one in it.
+    pub fn focus_window(&mut self, index: usize) {
+        if index == self.focused || index >= self.windows.len() {
+            return;
+        }
+        self.windows[self.focused] = self.window(self.focused);
+        self.enter_window(index);
+    }
+
+    /// Takes up the view of window `index`, switching to its buffer.
+    fn enter_window(&mut self, index: usize) {
+        let window = self.windows[index];
+        self.focused = index;
+        self.switch_buffer(window.buffer);
+        (self.cursor, self.rowoff, self.coloff) = (window.cursor, window.rowoff, window.coloff);
+        self.cursor.clamp_to(&self.buffer); // the buffer may have been edited in another window
+        self.reset_view_state();
+        self.fit_to_window();
+    }
+
+    /// Sizes the text area to the focused window, less its status bar.
+    fn fit_to_window(&mut self) {
+        let rect = self.window_rect(self.focused);
+        self.screenrows = rect.rows.saturating_sub(1).max(1); // a window with no room for text still scrolls as if it had a row
+        self.screencols = rect.cols.max(1);
+    }
+
+    /// Splits the focused window in two showing the same, and moves to the new half.
+    fn split_window(&mut self, split: Split) {
+        let new = self.windows.len();
+        if !self.layout.split(self.area, self.focused, split, new) {
+            self.set_status_message("No room to split the window");
+            return;
+        }
+        self.windows.push(self.window(self.focused));
+        self.focus_window(new);
+    }
+
+    /// Closes the focused window, not its buffer, and gives its room to the window next to it.
+    fn close_window(&mut self) {
+        let closed = self.focused;
+        match self.layout.remove(closed) {
+            Some(next) => {
+                self.windows.remove(closed);
+                self.enter_window(next);
+            }
+            None => self.set_status_message("Can't close the only window"),
+        }
+    }
+
+    /// Moves to the next window on the screen, from top left to bottom right and around.
+    fn next_window(&mut self) {
+        let order: Vec<usize> = self.layout.windows(self.area).into_iter().map(|(window, _)| window).collect();
+        let at = order.iter().position(|&window| window == self.focused).unwrap_or(0);
+        self.focus_window(order[(at + 1) % order.len()]);
+    }
+
+    /// Moves to the window in `direction` from the focused one, the one level with the cursor
+    /// if there are several.
+    fn focus_towards(&mut self, direction: Direction) {
+        let rect = self.window_rect(self.focused);
+        let row = rect.top + self.cursor.cy.saturating_sub(self.rowoff).min(self.screenrows.saturating_sub(1));
+        let col = rect.left + self.rx.saturating_sub(self.coloff).min(self.screencols.saturating_sub(1));
+        let cell = match direction {
+            Direction::Up => rect.top.checked_sub(1).map(|row| (row, col)),
+            Direction::Down => Some((rect.top + rect.rows, col)),
+            Direction::Left => rect.left.checked_sub(2).map(|col| (row, col)), // over the separator
+            Direction::Right => Some((row, rect.left + rect.cols + 1)),
+        };
+        if let Some(index) = cell.and_then(|(row, col)| self.layout.window_at(self.area, row, col)) {
+            self.focus_window(index);
+        }
+    }
+
+    /// Grows the focused window by `delta` rows or columns, depending on `along`.
+    fn resize_window(&mut self, along: Split, delta: isize) {
+        if self.layout.resize(self.area, self.focused, along, delta) {
+            self.fit_to_window();
+        } else {
+            let neighbour = if along == Split::Horizontal { "above or below" } else { "beside" };
+            self.set_status_message(&format!("No window {} to resize against", neighbour));
+        }
+    }
+
+    /*** block selection ***/
+
+    /// Moves the corner of the block selection one row or column, starting one at the cursor if
+    /// there is none. The cursor follows the corner as closely as the row lets it.
+    fn extend_block(&mut self, direction: Direction) {
+        let at = (self.cursor.cy, self.buffer.plain_row(self.cursor.cy).map_or(0, |row| row.cx_to_rx(self.cursor.cx)));
+        let block = self.block.get_or_insert(Block { anchor: at, corner: at });
+        let (row, rx) = &mut block.corner;
+        match direction {
+            Direction::Up => *row = row.saturating_sub(1),
+            Direction::Down => *row = (*row + 1).min(self.buffer.numrows().saturating_sub(1)),
+            Direction::Left => *rx = rx.saturating_sub(1),
+            Direction::Right => *rx += 1,
+        }
+        self.cursor_to_block_corner();
+    }
+
+    fn cursor_to_block_corner(&mut self) {
+        if let Some(Block { corner: (cy, rx), .. }) = self.block {
+            let cx = self.buffer.plain_row(cy).map_or(0, |row| row.rx_to_cx(rx));
+            self.cursor = Cursor::new(cx, cy);
+        }
+    }
+
+    /// Returns the `chars` the block covers on each of its rows.
+    fn block_ranges(&self, block: Block) -> Vec<(usize, Range<usize>)> {
+        block
+            .rows()
+            .filter_map(|cy| Some((cy, self.buffer.plain_row(cy)?.columns_to_cx(block.columns()))))
+            .collect()
+    }
+
+    /// Puts the text the block covers on the kill ring and the clipboard, one line per row.
+    fn copy_block(&mut self, block: Block) {
+        let lines: Vec<Vec<u8>> = self
+            .block_ranges(block)
+            .into_iter()
+            .map(|(cy, range)| self.buffer.text_between(Cursor::new(range.start, cy), Cursor::new(range.end, cy)))
+            .collect();
+        let text = lines.join(&b'\n');
+        self.export(&text);
+        self.kill_ring.push(Kill {
+            text,
+            whole_lines: false,
+        });
+    }
+
+    /// Deletes what the block covers on every row, or with no columns selected, the cluster
+    /// before the column (`forward` unset) or after it. What is left is a column to type into.
+    fn delete_block(&mut self, block: Block, forward: bool) {
+        let empty = block.columns().is_empty();
+        let mut left = block.columns().start;
+        for (cy, mut range) in self.block_ranges(block) {
+            let row = match self.buffer.plain_row(cy) {
+                Some(row) => row,
+                None => continue,
+            };
+            if empty && forward {
+                range.end = row.next_boundary(range.start);
+            } else if empty {
+                if row.cx_to_rx(range.start) != block.columns().start {
+                    continue; // the column is past the end of the row, or inside a cluster
+                }
+                range.start = row.prev_boundary(range.start);
+                if cy == block.corner.0 {
+                    left = row.cx_to_rx(range.start);
+                }
+            }
+            self.buffer.delete_region(Cursor::new(range.start, cy), Cursor::new(range.end, cy));
+        }
+        self.collapse_block(block, left);
+    }
+
+    /// Inserts `text` at the left edge of the block on every row, after deleting what it covers.
+    /// Rows that end before the block are padded with spaces up to it, so what is typed lines up,
+    /// and a tab the edge cuts through is turned into the spaces it stood for. A wide character
+    /// it cuts through stays whole, with `text` after it.
+    fn type_into_block(&mut self, block: Block, text: &[u8]) {
+        if !block.columns().is_empty() {
+            self.delete_block(block, false);
+        }
+        let left = block.columns().start;
+        let mut after = left;
+        for cy in block.rows() {
+            let row = match self.buffer.plain_row(cy) {
+                Some(row) => row,
+                None => continue,
+            };
+            let cut = row.cells().iter().find(|cell| cell.rx < left && cell.rx + cell.width > left).copied();
+            let (cx, mut inserted, rest) = match cut {
+                Some(cell) if row.chars[cell.cx] == b'\t' => {
+                    self.buffer.row_del_char(cy, cell.cx);
+                    (cell.cx, vec![b' '; left - cell.rx], vec![b' '; cell.rx + cell.width - left])
+                }
+                _ => {
+                    let width = row.cx_to_rx(row.len());
+                    (row.columns_to_cx(left..left).start, vec![b' '; left.saturating_sub(width)], Vec::new())
+                }
+            };
+            inserted.extend_from_slice(text);
+            let end = cx + inserted.len();
+            inserted.extend(rest);
+            self.buffer.row_insert_str(cy, cx, &inserted);
+            if cy == block.corner.0 {
+                after = self.buffer.plain_row(cy).map_or(left, |row| row.cx_to_rx(end));
+            }
+        }
+        self.collapse_block(block, after);
+    }
+
+    /// Turns the block into a column at screen column `rx` over the same rows.
+    fn collapse_block(&mut self, block: Block, rx: usize) {
+        self.block = Some(Block {
+            anchor: (block.anchor.0, rx),
+            corner: (block.corner.0, rx),
+        });
+        self.cursor_to_block_corner();
+    }
+
+    /// Handles keypress `c` as a block selection command, if it is one. Returns whether it was.
+    fn process_key_in_block(&mut self, c: Key) -> bool {
+        if c.modifiers == Modifiers::ALT | Modifiers::SHIFT {
+            let direction = match c.code {
+                KeyCode::Up => Direction::Up,
+                KeyCode::Down => Direction::Down,
+                KeyCode::Left => Direction::Left,
+                KeyCode::Right => Direction::Right,
+                _ => return false,
+            };
+            self.anchor = None;
+            self.others.clear();
+            self.extend_block(direction);
+            return true;
+        }
+        let block = match self.block {
+            Some(block) => block,
+            None => return false,
+        };
+
+        let before = self.cursor;
+        let kind = match (c.code, c.modifiers) {
+            (KeyCode::Char('c'), Modifiers::CTRL) => {
+                self.copy_block(block);
+                return true;
+            }
+            (KeyCode::Char('x'), Modifiers::CTRL) if !self.is_read_only() => {
+                self.copy_block(block);
+                self.delete_block(block, false);
+                EditKind::Other
+            }
+            (KeyCode::Backspace | KeyCode::Delete, _) | (KeyCode::Char('h'), Modifiers::CTRL)
+                if !self.is_read_only() =>
+            {
+                self.delete_block(block, c.code == KeyCode::Delete);
+                EditKind::Delete
+            }
+            (KeyCode::Char(ch), Modifiers::NONE) if !self.is_read_only() => {
+                let mut buf = [0; 4];
+                self.type_into_block(block, ch.encode_utf8(&mut buf).as_bytes());
+                EditKind::Insert
+            }
+            (KeyCode::Tab, Modifiers::NONE) if !self.is_read_only() => {
+                self.type_into_block(block, b"\t");
+                EditKind::Insert
+            }
+            _ => return false,
+        };
+        self.buffer.commit_edit(kind, before, self.cursor);
+        true
+    }
+
+    /*** multiple cursors ***/
+
+    /// Runs `f` with each cursor in turn as the main one, from the top of the buffer down.
+    /// The cursors further down are kept on the same text as `f` edits in front of them,
+    /// and cursors that end up in the same place become one.
+    fn for_each_cursor(&mut self, mut f: impl FnMut(&mut Editor)) {
+        let mut cursors: Vec<(usize, bool)> = std::iter::once((self.cursor, true))
+            .chain(self.others.iter().map(|&cursor| (cursor, false)))
+            .map(|(cursor, main)| (self.buffer.position(cursor), main))
+            .collect();
+        cursors.sort();
+
+        let mut shift = 0;
+        for (offset, _) in &mut cursors {
+            self.cursor = self.buffer.cursor_at(offset.saturating_add_signed(shift));
+            let len = self.buffer.text().len();
+            f(self);
+            shift += self.buffer.text().len() as isize - len as isize;
+            *offset = self.buffer.position(self.cursor);
+        }
+
+        cursors.dedup_by(|next, prev| {
+            prev.1 |= next.1 && next.0 == prev.0;
+            next.0 == prev.0
+        });
+        self.others.clear();
+        for (offset, main) in cursors {
+            let cursor = self.buffer.cursor_at(offset);
+            if main {
+                self.cursor = cursor;
+            } else {
+                self.others.push(cursor);
+            }
+        }
+    }
+
+    /// Handles keypress `c` at every cursor if it is one that makes sense there: typing,
+    /// deleting and moving without Shift. Returns whether it was.
+    fn process_key_at_cursors(&mut self, c: Key) -> bool {
+        let kind = match (c.code, c.modifiers) {
+            (KeyCode::Char(_) | KeyCode::Tab, Modifiers::NONE) => Some(EditKind::Insert),
+            (KeyCode::Enter, _) => Some(EditKind::Other),
+            (KeyCode::Backspace | KeyCode::Delete, _) | (KeyCode::Char('h'), Modifiers::CTRL) => {
+                Some(EditKind::Delete)
+            }
+            (KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End, m)
+                if m.is_empty() =>
+            {
+                None
+            }
+            _ => return false,
+        };
+        if kind.is_some() && self.is_read_only() {
+            return true;
+        }
+
+        self.anchor = None;
+        let before = self.cursor;
+        self.for_each_cursor(|editor| match (c.code, c.modifiers) {
+            (KeyCode::Backspace, _) | (KeyCode::Char('h'), Modifiers::CTRL) => editor.del_char(),
+            (KeyCode::Delete, _) => {
+                editor.cursor.move_to(Direction::Right, &editor.buffer);
+                editor.del_char();
+            }
+            (KeyCode::Char(ch), _) => editor.insert_char(ch),
+            (KeyCode::Tab, _) => editor.insert_char('\t'),
+            (KeyCode::Enter, _) => editor.insert_newline(),
+            (KeyCode::Up, _) => editor.cursor.move_to(Direction::Up, &editor.buffer),
+            (KeyCode::Down, _) => editor.cursor.move_to(Direction::Down, &editor.buffer),
+            (KeyCode::Left, _) => editor.cursor.move_to(Direction::Left, &editor.buffer),
+            (KeyCode::Right, _) => editor.cursor.move_to(Direction::Right, &editor.buffer),
+            (KeyCode::Home, _) => editor.cursor.home(),
+            (KeyCode::End, _) => editor.cursor.end(&editor.buffer),
+            _ => {}
+        });
+        if let Some(kind) = kind {
+            self.buffer.commit_edit(kind, before, self.cursor);
+        }
+        true
+    }
+
+    /// Adds a cursor on the row above the topmost cursor, or below the bottommost one.
+    fn add_cursor(&mut self, direction: Direction) {
+        let cursors = self.cursors();
+        let edge = match direction {
+            Direction::Up => cursors[0],
+            _ => cursors[cursors.len() - 1],
+        };
+        let mut cursor = edge;
+        cursor.move_to(direction, &self.buffer);
+        if cursor.cy == edge.cy || cursor.cy == self.buffer.numrows() {
+            return;
+        }
+        self.others.push(cursor);
+        self.others.sort();
+    }
+
+    /// Adds a cursor at the next occurrence of the word under the cursor, after the last cursor,
+    /// as far into the word as the cursor is. Goes back to the top after the end of the buffer.
+    fn add_cursor_at_next_word(&mut self) {
+        let row = match self.buffer.plain_row(self.cursor.cy) {
+            Some(row) => row,
+            None => return,
+        };
+        let (chars, cx) = (row.chars(), self.cursor.cx);
+        let start = chars[..cx].iter().rposition(|&c| !is_word_byte(c)).map_or(0, |i| i + 1);
+        let end = chars[cx..].iter().position(|&c| !is_word_byte(c)).map_or(chars.len(), |i| cx + i);
+        if start == end {
+            self.set_status_message("No word under the cursor");
+            return;
+        }
+        let (word, into) = (&chars[start..end], cx - start);
+
+        let cursors = self.cursors();
+        let last = cursors[cursors.len() - 1];
+        let numrows = self.buffer.numrows();
+        for i in 0..=numrows {
+            let cy = (last.cy + i) % numrows;
+            let row = match self.buffer.plain_row(cy) {
+                Some(row) => row,
+                None => continue,
+            };
+            let chars = row.chars();
+            let mut at = 0;
+            while let Some(found) = find_bytes(&chars[at..], word) {
+                let (start, end) = (at + found, at + found + word.len());
+                at = start + 1;
+                let whole_word = (start == 0 || !is_word_byte(chars[start - 1]))
+                    && chars.get(end).is_none_or(|&c| !is_word_byte(c));
+                let cursor = Cursor::new(start + into, cy);
+                if whole_word && (i > 0 || cursor.cx > last.cx) && !cursors.contains(&cursor) {
+                    self.others.push(cursor);
+                    self.others.sort();
+                    self.set_status_message(&format!("{} cursors", self.others.len() + 1));
+                    return;
+                }
+            }
+        }
+        self.set_status_message(&format!("No more of \"{}\"", String::from_utf8_lossy(word)));
+    }
+
+    /*** clipboard ***/
+
+    /// Puts the selection, or the cursor's row and its line break without one, on the kill ring.
+    /// Returns what it took, or `None` if there was nothing there.
+    fn copy(&mut self, selection: Option<(Cursor, Cursor)>) -> Option<(Cursor, Cursor)> {
+        let (region, whole_lines) = match selection {
+            Some(selection) => (selection, false),
+            None if self.cursor.cy < self.buffer.numrows() => {
+                let cy = self.cursor.cy;
+                ((Cursor::new(0, cy), Cursor::new(0, cy + 1)), true)
+            }
+            None => return None,
+        };
+        let text = self.buffer.text_between(region.0, region.1);
+        self.export(&text);
+        self.kill_ring.push(Kill { text, whole_lines });
+        Some(region)
+    }
+
+    /// Hands `text` to the system clipboard, through the terminal and to the copy command.
+    fn export(&mut self, text: &[u8]) {
+        if self.clipboard.osc52 {
+            self.terminal_out.extend(clipboard::osc52(text));
+        }
+        if let Err(err) = self.clipboard.copy(text) {
+            self.set_status_message(&format!("Can't run the copy command: {}", err));
+        }
+    }
+
+    /// Puts what the paste command prints on the kill ring, unless it is the newest entry already.
+    /// Without a paste command, or if it fails, pasting falls back to what is on the kill ring.
+    fn import(&mut self) {
+        match self.clipboard.paste() {
+            Some(Ok(text)) if self.kill_ring.get(0).is_none_or(|kill| kill.text != text) => {
+                self.kill_ring.push(Kill {
+                    text,
+                    whole_lines: false,
+                });
+            }
+            Some(Err(err)) => self.set_status_message(&format!("Can't run the paste command: {}", err)),
+            _ => {}
+        }
+    }
+
+    fn cut(&mut self, selection: Option<(Cursor, Cursor)>) {
+        if self.is_read_only() {
+            return;
+        }
+        match self.copy(selection) {
+            Some(region) => {
+                let cx = self.cursor.cx;
+                self.delete_region(region);
+                if selection.is_none() {
+                    self.cursor.cx = cx; // stay in the same column of the row that moved up
+                    self.cursor.clamp_to(&self.buffer);
+                }
+            }
+            None => self.set_status_message("Nothing to cut"),
+        }
+    }
+
+    /// Inserts entry `index` of the kill ring at the cursor, or above the cursor's row if it was
+    /// a whole line, and leaves the cursor after it.
+    fn paste(&mut self, index: usize) {
+        if self.is_read_only() {
+            return;
+        }
+        let kill = match self.kill_ring.get(index) {
+            Some(kill) => kill.clone(),
+            None => {
+                self.set_status_message("Nothing to paste");
+                return;
+            }
+        };
+        if kill.whole_lines {
+            self.cursor.cx = 0;
+        }
+        let start = self.cursor;
+        self.insert_text(&kill.text);
+        self.yank = Some(Yank {
+            start,
+            end: self.cursor,
+            index,
+        });
+    }
+
+    /// Swaps the text just pasted for the entry of the kill ring before it.
+    fn cycle_paste(&mut self, yank: Option<Yank>) {
+        if self.is_read_only() {
+            return;
+        }
+        let yank = match yank {
+            Some(yank) => yank,
+            None => {
+                self.set_status_message("Paste with Ctrl-V first");
+                return;
+            }
+        };
+        self.delete_region((yank.start, yank.end));
+        let index = (yank.index + 1) % self.kill_ring.len();
+        self.paste(index);
+        self.set_status_message(&format!("Pasted entry {} of {}", index + 1, self.kill_ring.len()));
+    }
+
+    /*** undo ***/
+
+    fn undo(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        match self.buffer.undo() {
+            Some(cursor) => self.cursor = cursor,
+            None => self.set_status_message("Already at oldest change"),
+        }
+        self.cursor.clamp_to(&self.buffer);
+    }
+
+    fn redo(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        match self.buffer.redo() {
+            Some(cursor) => self.cursor = cursor,
+            None => self.set_status_message("Already at newest change"),
+        }
+        self.cursor.clamp_to(&self.buffer);
+    }
+
+    /// Moves to the state `travel` says, on whatever branch of the undo tree it is.
+    fn travel(&mut self, travel: Travel) {
+        if self.is_read_only() {
+            return;
+        }
+        match self.buffer.travel(travel) {
+            Some(cursor) => {
+                self.cursor = cursor;
+                let history = self.buffer.history();
+                self.set_status_message(&format!("At change {} of {}", history.state(), history.last_state()));
+            }
+            None if matches!(travel, Travel::Steps(..0) | Travel::Seconds(..0)) => {
+                self.set_status_message("Already at oldest change")
+            }
+            None => self.set_status_message("Already at newest change"),
+        }
+        self.cursor.clamp_to(&self.buffer);
+    }
+
+    fn next_branch(&mut self) {
+        match self.buffer.next_branch() {
+            Some((branch, count)) => self.set_status_message(&format!("Redo follows branch {} of {}", branch, count)),
+            None => self.set_status_message("No other branch to redo here"),
+        }
+    }
+
+    fn time_travel(&mut self) {
+        self.prompt("Go to: {} (earlier/later N, Ns, Nm, Nh or Nd)", Some(PromptKind::Command), None, Editor::time_travel_done);
+    }
+
+    fn time_travel_done(&mut self, answer: Option<String>) {
+        match answer.as_deref().map(Travel::parse) {
+            Some(Some(travel)) => self.travel(travel),
+            Some(None) => self.set_status_message("Expected something like \"earlier 5m\" or \"later 3\""),
+            None => {}
+        }
+    }
+
+    /*** file i/o ***/
+
+    fn save(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        if self.buffer.filename().is_none() {
+            self.prompt("Save as: {} (ESC to cancel)", Some(PromptKind::File), None, Editor::save_as_done);
+            return;
+        }
+
+        match self.buffer.save() {
+            Ok(len) => {
+                let mut msg = format!("{} bytes written to disk", len);
+                if self.persistent_undo {
+                    if let Err(err) = self.buffer.save_history() {
+                        msg += &format!(", but can't save undo history: {}", err);
+                    }
+                }
+                self.set_status_message(&msg);
+            }
+            Err(err) => self.set_status_message(&format!("Can't save! I/O error: {}", err)),
+        }
+    }
+
+    fn save_as_done(&mut self, filename: Option<String>) {
+        match filename {
+            Some(filename) => {
+                self.buffer.set_filename(&filename);
+                self.save();
+            }
+            None => self.set_status_message("Save aborted"),
+        }
+    }
+
+    /*** find ***/
+
+    /// Returns the template of a search prompt, with the options that are on.
+    fn search_template(&self, label: &str) -> String {
+        let options = match self.search_options.describe() {
+            options if options.is_empty() => options,
+            options => format!(" ({})", options),
+        };
+        format!("{}{}: {{}} (ESC/Enter, Left/Right match, Up/Down history, Alt-C case, Alt-W word, Alt-R regex)", label, options)
+    }
+
+    /// Moves to the match from `start` to `end` and highlights it.
+    fn show_match(&mut self, (start, end): (Cursor, Cursor)) {
+        self.find.current = Some((start, end));
+        self.cursor = start;
+        self.rowoff = self.buffer.numrows(); // scroll so the match ends up at the top of the screen
+    }
+
+    fn find_callback(&mut self, prompt: &mut Prompt, key: Key) {
+        let options = &mut self.search_options;
+        match (key.code, key.modifiers) {
+            (KeyCode::Enter, _) => {
+                self.find.current = None;
+                return;
+            }
+            (KeyCode::Esc, _) => {
+                self.find.current = None;
+                self.set_search(None);
+                return;
+            }
+            (KeyCode::Char('c'), Modifiers::ALT) => options.ignore_case = !options.ignore_case,
+            (KeyCode::Char('w'), Modifiers::ALT) => options.whole_word = !options.whole_word,
+            (KeyCode::Char('r'), Modifiers::ALT) => options.regex = !options.regex,
+            _ => {}
+        }
+        let label = prompt.template.split([':', ' ']).next().unwrap_or_default().to_string();
+        prompt.template = self.search_template(&label);
+        self.set_search(None);
+        if prompt.buf.is_empty() {
+            return;
+        }
+        let matcher = match Matcher::new(&prompt.buf, self.search_options) {
+            Ok(matcher) => matcher,
+            Err(_) => {
+                prompt.template = prompt.template.replacen("{}", "{} (not a valid regex)", 1);
+                return;
+            }
+        };
+        self.set_search(Some(matcher.clone()));
+
+        let found = match (key.code, self.find.current) {
+            (KeyCode::Right, Some((start, _))) => {
+                // Past the first char of the match, which can be the line break at the end of a row.
+                let text = self.buffer.text();
+                let next = text.char_to_byte(text.byte_to_char(self.buffer.position(start)) + 1);
+                matcher.find_forward(&self.buffer, self.buffer.cursor_at(next), true)
+            }
+            (KeyCode::Left, Some((start, _))) => matcher.find_backward(&self.buffer, start),
+            (KeyCode::Left, None) => matcher.find_backward(&self.buffer, self.find.saved_view.0),
+            _ => matcher.find_forward(&self.buffer, self.find.saved_view.0, true), // from where the search started
+        };
+        self.find.current = None;
+        if let Some(found) = found {
+            self.show_match(found);
+        }
+    }
+
+    /// Highlights every match of `search`, or none.
+    fn set_search(&mut self, search: Option<Matcher>) {
+        self.find.search = search;
+        self.find.matches = None;
+        self.find.matched = None;
+        self.find.count_due = false;
+    }
+
+    /// Counts the matches of the search again if the buffer has been edited since they were
+    /// counted. Not while a file is loading, and in a big text only once the search is
+    /// confirmed, since that goes through all of it.
+    pub(crate) fn update_matches(&mut self) {
+        let search = match &self.find.search {
+            Some(search) => search,
+            None => return,
+        };
+        let changes = self.buffer.changes();
+        if self.buffer.is_loading() || (self.find.matched == Some(changes) && !self.find.count_due) {
+            return;
+        }
+        self.find.matches = match self.buffer.text().len() > COUNT_MAX_LEN && !self.find.count_due {
+            true => None,
+            false => Some(search.find_everywhere(&self.buffer)),
+        };
+        self.find.matched = Some(changes);
+        self.find.count_due = false;
+    }
+
+    /// Returns the number of the match at the cursor and how many there are, or `None` with no
+    /// search or no count of its matches. The number is `None` if the cursor isn't at the start of a match.
+    pub fn match_count(&self) -> Option<(Option<usize>, usize)> {
+        let matches = self.find.matches.as_ref()?;
+        let at = matches.binary_search_by(|(start, _)| start.cmp(&self.cursor)).ok();
+        Some((at.map(|i| i + 1), matches.len()))
+    }
+
+    /// Returns the matches of the search on `rows` of the buffer, to draw them.
+    pub(crate) fn visible_matches(&self, rows: Range<usize>) -> Vec<(Cursor, Cursor)> {
+        match &self.find.search {
+            Some(search) => search.find_in_rows(&self.buffer, rows),
+            None => Vec::new(),
+        }
+    }
+
+    fn find(&mut self) {
+        self.find.saved_view = (self.cursor, self.coloff, self.rowoff);
+        self.prompt(&self.search_template("Search"), Some(PromptKind::Search), Some(Editor::find_callback), Editor::find_done);
+    }
+
+    fn find_done(&mut self, query: Option<String>) {
+        match query {
+            Some(_) => self.find.count_due = self.find.search.is_some(),
+            None => {
+                (self.cursor, self.coloff, self.rowoff) = self.find.saved_view;
+                self.set_search(None);
+            }
+        }
+    }
+
+    /// Asks what to replace, what with, and then goes through the matches from the top, asking
+    /// about each one. The replacements make up one step of the undo history.
+    fn replace(&mut self) {
+        if self.is_read_only() {
+            return;
+        }
+        self.find.saved_view = (self.cursor, self.coloff, self.rowoff);
+        self.prompt(&self.search_template("Replace"), Some(PromptKind::Search), Some(Editor::find_callback), Editor::replace_pattern_done);
+    }
+
+    fn replace_pattern_done(&mut self, pattern: Option<String>) {
+        let pattern = match pattern {
+            Some(pattern) => pattern,
+            None => {
+                self.find_done(None);
+                return;
+            }
+        };
+        match Matcher::new(&pattern, self.search_options) {
+            Ok(matcher) => self.find.matcher = Some(matcher),
+            Err(_) => {
+                self.find_done(None);
+                self.set_status_message(&format!("Not a valid regex: {}", pattern));
+                return;
+            }
+        }
+        let template = format!("Replace \"{}\" with: {{}} ($1 for the first group of a regex, ESC to cancel)", pattern);
+        self.prompt(&template, Some(PromptKind::Replace), Some(Editor::replace_with_callback), Editor::replace_with_done);
+    }
+
+    /// Lets Enter take an empty answer, to replace with nothing.
+    fn replace_with_callback(&mut self, prompt: &mut Prompt, key: Key) {
+        if key.code == KeyCode::Enter && prompt.buf.is_empty() {
+            prompt.finished = true;
+        }
+    }
+
+    fn replace_with_done(&mut self, with: Option<String>) {
+        let with = match with {
+            Some(with) => with,
+            None => {
+                self.find.matcher = None;
+                self.find_done(None);
+                return;
+            }
+        };
+        self.find.with = with;
+        self.find.replaced = 0;
+        let first = self.find.matcher.as_ref().and_then(|m| m.find_forward(&self.buffer, Cursor::default(), false));
+        match first {
+            Some(first) => {
+                self.show_match(first);
+                let template = "Replace this one? y = yes, n = no, a = all the rest, ESC = stop{}";
+                self.prompt(template, None, Some(Editor::replace_callback), Editor::replace_done);
+            }
+            None => {
+                self.find.matcher = None;
+                self.find_done(None);
+                self.set_status_message("No matches");
+            }
+        }
+    }
+
+    fn replace_callback(&mut self, prompt: &mut Prompt, key: Key) {
+        prompt.buf.clear();
+        let (start, end) = match self.find.current {
+            Some(current) => current,
+            None => return,
+        };
+        let next = match (key.code, key.modifiers) {
+            (KeyCode::Char('y'), Modifiers::NONE) => self.replace_match(start, end),
+            (KeyCode::Char('n'), Modifiers::NONE) => end,
+            (KeyCode::Char('a'), Modifiers::NONE) => {
+                let mut from = start;
+                while let Some((start, end)) = self.next_replace_match(from) {
+                    from = self.replace_match(start, end);
+                }
+                self.cursor = from;
+                prompt.finished = true;
+                return;
+            }
+            _ => {
+                self.show_match((start, end));
+                return;
+            }
+        };
+        match self.next_replace_match(next) {
+            Some(found) => self.show_match(found),
+            None => {
+                self.cursor = next;
+                prompt.finished = true;
+            }
+        }
+    }
+
+    /// Returns the next match to replace at `from` or after it, without going around to the top.
+    fn next_replace_match(&self, from: Cursor) -> Option<(Cursor, Cursor)> {
+        self.find.matcher.as_ref()?.find_forward(&self.buffer, from, false)
+    }
+
+    /// Replaces the match from `start` to `end` and returns where the replacement ends.
+    fn replace_match(&mut self, start: Cursor, end: Cursor) -> Cursor {
+        let text = match &self.find.matcher {
+            Some(matcher) => matcher.replacement(&self.buffer, (start, end), &self.find.with),
+            None => return end,
+        };
+        self.buffer.delete_region(start, end);
+        self.find.replaced += 1;
+        if text.is_empty() {
+            return start;
+        }
+        let (cx, cy) = self.buffer.insert_text(start.cy, start.cx, &text);
+        Cursor::new(cx, cy)
+    }
+
+    fn replace_done(&mut self, _: Option<String>) {
+        let before = self.find.saved_view.0;
+        self.cursor.clamp_to(&self.buffer);
+        self.buffer.commit_edit(EditKind::Other, before, self.cursor);
+        let replaced = self.find.replaced;
+        self.find = FindState::default();
+        self.set_status_message(&match replaced {
+            1 => "Replaced 1 match".to_string(),
+            n => format!("Replaced {} matches", n),
+        });
+    }
+
+    /*** mouse ***/
+
+    /// Maps a cell of the text area to a position in the buffer, going through `rowoff`/`coloff`
+    /// and `rx_to_cx` so a click on the blanks of a tab lands on the tab itself. Rows above the
+    /// text area count back from -1, to the rows before the first one shown.
+    fn screen_to_buffer(&self, column: usize, row: isize) -> Cursor {
+        let cy = self.rowoff.saturating_add_signed(row).min(self.buffer.numrows());
+        let cx = self.buffer.plain_row(cy).map_or(0, |r| r.rx_to_cx(self.coloff + column));
+        Cursor::new(cx, cy)
+    }
+
+    /// Scrolls the view by `delta` rows, dragging the cursor along when it would leave the screen
+    /// (otherwise the next refresh would scroll right back to it).
+    fn scroll_rows(&mut self, delta: isize) {
+        let max_rowoff = self.buffer.numrows().saturating_sub(1);
+        self.rowoff = self.rowoff.saturating_add_signed(delta).min(max_rowoff);
+
+        if self.cursor.cy < self.rowoff {
+            self.cursor.cy = self.rowoff;
+        } else if self.cursor.cy >= self.rowoff + self.screenrows {
+            self.cursor.cy = (self.rowoff + self.screenrows).saturating_sub(1);
+        }
+        self.cursor.clamp_to(&self.buffer);
+    }
+
+    fn process_mouse(&mut self, mouse: MouseEvent) {
+        // Drags and releases go on to the window, for a selection dragged up past its top.
+        if self.tab_line && mouse.row == 0 && !matches!(mouse.kind, MouseKind::Drag(_) | MouseKind::Release(_)) {
+            if mouse.kind == MouseKind::Press(MouseButton::Left) {
+                let tabs = self.tabs();
+                if let Some((index, ..)) = tabs.into_iter().find(|(.., columns)| columns.contains(&mouse.column)) {
+                    self.switch_buffer(index);
+                }
+            }
+            return;
+        }
+        // Clicking or scrolling over another window moves to it first.
+        if matches!(mouse.kind, MouseKind::Press(_) | MouseKind::ScrollUp | MouseKind::ScrollDown) {
+            if let Some(index) = self.layout.window_at(self.area, mouse.row, mouse.column) {
+                self.focus_window(index);
+            }
+        }
+        // From here on the mouse is placed within the focused window.
+        let rect = self.window_rect(self.focused);
+        let inside = rect.contains(mouse.row, mouse.column);
+        let row = mouse.row as isize - rect.top as isize;
+        let column = mouse.column.saturating_sub(rect.left);
+        match mouse.kind {
+            // Dragging with Alt held selects a block.
+            MouseKind::Press(MouseButton::Left) if inside && row < self.screenrows as isize => {
+                self.cursor = self.screen_to_buffer(column, row);
+                self.anchor = None;
+                self.block = None;
+                if mouse.modifiers.contains(Modifiers::ALT) {
+                    let at = (self.cursor.cy, self.coloff + column);
+                    self.block = Some(Block { anchor: at, corner: at });
+                } else {
+                    self.anchor = Some(self.cursor);
+                }
+            }
+            MouseKind::Drag(MouseButton::Left) if self.block.is_some() => {
+                let cy = self.screen_to_buffer(column, row).cy;
+                let cy = cy.min(self.buffer.numrows().saturating_sub(1));
+                if let Some(block) = &mut self.block {
+                    block.corner = (cy, self.coloff + column);
+                }
+                self.cursor_to_block_corner();
+            }
+            MouseKind::Release(_) if self.block.is_some_and(|block| block.anchor == block.corner) => self.block = None,
+            MouseKind::Drag(MouseButton::Left) if self.anchor.is_some() => {
+                // Dragging past the bottom or the top of the text area moves the cursor off
+                // screen, so the next refresh scrolls to it.
+                self.cursor = self.screen_to_buffer(column, row);
+            }
+            MouseKind::Release(_) if self.selection().is_none() => self.anchor = None,
+            MouseKind::ScrollUp => self.scroll_rows(-(MOUSE_SCROLL_ROWS as isize)),
+            MouseKind::ScrollDown => self.scroll_rows(MOUSE_SCROLL_ROWS as isize),
+            _ => {}
+        }
+    }
+
+    /*** input ***/
+
+    /// Starts showing `template` in the message bar, with `{}` replaced by what the user has typed so far.
+    ///
+    /// Keypresses go to the prompt until the user presses Enter or Escape, then `done` gets the answer.
+    fn prompt(&mut self, template: &str, kind: Option<PromptKind>, callback: Option<PromptCallback>, done: PromptDone) {
+        self.set_status_message(&template.replace("{}", ""));
+        self.prompt = Some(Prompt {
+            template: template.to_string(),
+            buf: String::new(),
+            callback,
+            done,
+            finished: false,
+            kind,
+            back: None,
+            typed: String::new(),
+        });
+    }
+
+    /// Brings the previous answer of the prompt's history into it, or the next one if `older`
+    /// isn't set, and past the newest what was typed before going back.
+    fn prompt_recall(&self, prompt: &mut Prompt, older: bool) {
+        let kind = match prompt.kind {
+            Some(kind) => kind,
+            None => return,
+        };
+        let back = match (prompt.back, older) {
+            (None, true) => 0,
+            (Some(back), true) => back + 1,
+            (None, false) => return,
+            (Some(0), false) => {
+                prompt.buf = std::mem::take(&mut prompt.typed);
+                prompt.back = None;
+                return;
+            }
+            (Some(back), false) => back - 1,
+        };
+        let entry = match self.prompt_history.get(kind, back) {
+            Some(entry) => entry.to_string(),
+            None => return,
+        };
+        if prompt.back.is_none() {
+            prompt.typed = std::mem::take(&mut prompt.buf);
+        }
+        prompt.buf = entry;
+        prompt.back = Some(back);
+    }
+
+    /// Adds the answer to a prompt to its history, and saves the history if it is kept.
+    fn prompt_remember(&mut self, prompt: &Prompt) {
+        let kind = match prompt.kind {
+            Some(kind) => kind,
+            None => return,
+        };
+        self.prompt_history.add(kind, &prompt.buf);
+        if let Some(path) = &self.prompt_history_file {
+            if let Err(err) = self.prompt_history.save(path) {
+                self.set_status_message(&format!("Can't save prompt history: {}", err));
+            }
+        }
+    }
+
+    fn prompt_process_key(&mut self, mut prompt: Prompt, c: Key) {
+        match (c.code, c.modifiers) {
+            (KeyCode::Delete | KeyCode::Backspace, _) | (KeyCode::Char('h'), Modifiers::CTRL) => {
+                prompt.buf.pop();
+            }
+            (KeyCode::Esc, _) => {
+                self.set_status_message("");
+                if let Some(callback) = prompt.callback {
+                    callback(self, &mut prompt, c);
+                }
+                (prompt.done)(self, None);
+                return;
+            }
+            (KeyCode::Enter, _) if !prompt.buf.is_empty() => {
+                self.set_status_message("");
+                self.prompt_remember(&prompt);
+                if let Some(callback) = prompt.callback {
+                    callback(self, &mut prompt, c);
+                }
+                (prompt.done)(self, Some(prompt.buf));
+                return;
+            }
+            (KeyCode::Up, Modifiers::NONE) if prompt.kind.is_some() => self.prompt_recall(&mut prompt, true),
+            (KeyCode::Down, Modifiers::NONE) if prompt.kind.is_some() => self.prompt_recall(&mut prompt, false),
+            (KeyCode::Char(ch), Modifiers::NONE) => prompt.buf.push(ch),
+            _ => {}
+        }
+
+        if let Some(callback) = prompt.callback {
+            callback(self, &mut prompt, c);
+        }
+        if prompt.finished {
+            self.set_status_message("");
+            self.prompt_remember(&prompt);
+            (prompt.done)(self, Some(prompt.buf));
+            return;
+        }
+        self.set_status_message(&prompt.template.replace("{}", &prompt.buf));
+        self.prompt = Some(prompt);
+    }
+
+    /// Handles one event from the terminal. Returns `false` once the editor should quit.
+    pub fn process_event(&mut self, event: Event) -> bool {
+        if !matches!(event, Event::Key(_)) {
+            self.yank = None; // the text pasted may have moved
+        }
+        match event {
+            Event::Key(key) => self.process_key(key),
+            Event::Mouse(_) if self.prompt.is_some() => true,
+            Event::Mouse(mouse) => {
+                if matches!(mouse.kind, MouseKind::Press(_)) {
+                    self.others.clear();
+                }
+                self.process_mouse(mouse);
+                true
+            }
+            Event::Paste(text) => {
+                match (self.prompt.as_mut(), self.block) {
+                    // A prompt is a single line, so only the first line of the paste goes into it.
+                    (Some(prompt), _) => {
+                        let line = text.lines().next().unwrap_or_default();
+                        prompt.buf.extend(line.chars().filter(|c| !c.is_control()));
+                        let msg = prompt.template.replace("{}", &prompt.buf);
+                        self.set_status_message(&msg);
+                    }
+                    // A paste of one line goes into every row of a block, like what is typed.
+                    (None, Some(block)) if !text.contains('\n') => {
+                        if !self.is_read_only() {
+                            let before = self.cursor;
+                            self.type_into_block(block, text.as_bytes());
+                            self.buffer.commit_edit(EditKind::Other, before, self.cursor);
+                        }
+                    }
+                    (None, _) => {
+                        let before = self.cursor;
+                        self.block = None;
+                        if let Some(selection) = self.selection() {
+                            self.delete_region(selection);
+                        }
+                        self.anchor = None;
+                        self.for_each_cursor(|editor| editor.insert_text(text.as_bytes()));
+                        self.buffer.commit_edit(EditKind::Other, before, self.cursor);
+                    }
+                }
+                self.quit_times = QUIT_TIMES;
+                self.close_confirmed = false;
+                true
+            }
+        }
+    }
+
+    /// Handles one keypress. Returns `false` once the editor should quit.
+    pub fn process_key(&mut self, c: Key) -> bool {
+        if let Some(prompt) = self.prompt.take() {
+            self.prompt_process_key(prompt, c);
+            return true;
+        }
+
+        if !self.others.is_empty() {
+            if self.process_key_at_cursors(c) {
+                self.quit_times = QUIT_TIMES;
+                self.close_confirmed = false;
+                return true;
+            }
+            // Anything else works at the main cursor only, except adding more cursors.
+            let adding = matches!(
+                (c.code, c.modifiers),
+                (KeyCode::Char('d'), Modifiers::CTRL) | (KeyCode::Up | KeyCode::Down, Modifiers::ALT)
+            );
+            if !adding {
+                self.others.clear();
+            }
+        }
+
+        if self.process_key_in_block(c) {
+            self.quit_times = QUIT_TIMES;
+            self.close_confirmed = false;
+            return true;
+        }
+        self.block = None;
+
+        // Moving with Shift held starts or extends the selection, anything else drops it.
+        let selection = self.selection();
+        let anchor = self.anchor.take();
+        let yank = self.yank.take();
+        let selecting = c.modifiers == Modifiers::SHIFT
+            && matches!(
+                c.code,
+                KeyCode::Up
+                    | KeyCode::Down
+                    | KeyCode::Left
+                    | KeyCode::Right
+                    | KeyCode::Home
+                    | KeyCode::End
+                    | KeyCode::PageUp
+                    | KeyCode::PageDown
+            );
+        if selecting {
+            self.anchor = Some(anchor.unwrap_or(self.cursor));
+        }
+
+        let before = self.cursor;
+        match (c.code, c.modifiers) {
+            (KeyCode::Enter, _) => {
+                if let Some(selection) = selection {
+                    self.delete_region(selection);
+                }
+                self.insert_newline();
+                self.buffer.commit_edit(EditKind::Other, before, self.cursor);
+            }
+
+            (KeyCode::Char('q'), Modifiers::CTRL) => {
+                let dirty = self.dirty_buffers();
+                if dirty > 0 && self.quit_times > 0 {
+                    let files = match dirty {
+                        1 => "File has".to_string(),
+                        n => format!("{} files have", n),
+                    };
+                    self.set_status_message(&format!(
+                        "WARNING!!! {} unsaved changes. Press Ctrl-Q {} more times to quit.",
+                        files, self.quit_times
+                    ));
+                    self.quit_times -= 1;
+                    return true;
+                }
+                return false;
+            }
+
+            (KeyCode::Char('o'), Modifiers::CTRL) => self.open_prompt(),
+            (KeyCode::Char('w'), Modifiers::CTRL) => {
+                self.close_buffer();
+                if self.close_confirmed {
+                    return true;
+                }
+            }
+            (KeyCode::Char('b'), Modifiers::CTRL) => self.list_buffers(),
+            (KeyCode::Char(n @ '1'..='9'), Modifiers::ALT) => {
+                let index = n as usize - '1' as usize;
+                match index < self.buffers.len() {
+                    true => self.switch_buffer(index),
+                    false => self.set_status_message(&format!("There is no buffer {}", n)),
+                }
+            }
+            (KeyCode::PageDown, Modifiers::CTRL) => self.switch_buffer((self.current + 1) % self.buffers.len()),
+            (KeyCode::PageUp, Modifiers::CTRL) => {
+                self.switch_buffer((self.current + self.buffers.len() - 1) % self.buffers.len())
+            }
+
+            (KeyCode::Char('s'), Modifiers::ALT) => self.split_window(Split::Horizontal),
+            (KeyCode::Char('S'), Modifiers::ALT) => self.split_window(Split::Vertical),
+            (KeyCode::Char('q'), Modifiers::ALT) => self.close_window(),
+            (KeyCode::Char('w'), Modifiers::ALT) => self.next_window(),
+            (KeyCode::Char('h'), Modifiers::ALT) => self.focus_towards(Direction::Left),
+            (KeyCode::Char('j'), Modifiers::ALT) => self.focus_towards(Direction::Down),
+            (KeyCode::Char('k'), Modifiers::ALT) => self.focus_towards(Direction::Up),
+            (KeyCode::Char('l'), Modifiers::ALT) => self.focus_towards(Direction::Right),
+            (KeyCode::Char('='), Modifiers::ALT) => self.resize_window(Split::Horizontal, 1),
+            (KeyCode::Char('-'), Modifiers::ALT) => self.resize_window(Split::Horizontal, -1),
+            (KeyCode::Char('.'), Modifiers::ALT) => self.resize_window(Split::Vertical, 1),
+            (KeyCode::Char(','), Modifiers::ALT) => self.resize_window(Split::Vertical, -1),
+
+            (KeyCode::Char('s'), Modifiers::CTRL) => self.save(),
+
+            (KeyCode::Home, _) => self.cursor.home(),
+            (KeyCode::End, _) => self.cursor.end(&self.buffer),
+
+            (KeyCode::Char('f'), Modifiers::CTRL) => self.find(),
+            (KeyCode::Char('r'), Modifiers::CTRL) => self.replace(),
+
+            // Ctrl-H sends the control code 8, which is what Backspace used to send back in the day.
+            (KeyCode::Backspace | KeyCode::Delete, _) | (KeyCode::Char('h'), Modifiers::CTRL) => match selection {
+                Some(selection) => {
+                    self.delete_region(selection);
+                    self.buffer.commit_edit(EditKind::Other, before, self.cursor);
+                }
+                None => {
+                    if c.code == KeyCode::Delete {
+                        self.cursor.move_to(Direction::Right, &self.buffer); // move cursor right on 'delete'
+                    }
+                    self.del_char();
+                    self.buffer.commit_edit(EditKind::Delete, before, self.cursor);
+                }
+            },
+
+            (KeyCode::PageUp | KeyCode::PageDown, _) => {
+                let direction = if c.code == KeyCode::PageUp {
+                    self.cursor.cy = self.rowoff;
+                    Direction::Up
+                } else {
+                    self.cursor.cy = (self.rowoff + self.screenrows).saturating_sub(1).min(self.buffer.numrows());
+                    Direction::Down
+                };
+
+                for _ in 0..self.screenrows {
+                    self.cursor.move_to(direction, &self.buffer);
+                }
+            }
+
+            (KeyCode::Up, Modifiers::ALT) => self.add_cursor(Direction::Up),
+            (KeyCode::Down, Modifiers::ALT) => self.add_cursor(Direction::Down),
+            (KeyCode::Char('d'), Modifiers::CTRL) => self.add_cursor_at_next_word(),
+
+            (KeyCode::Up, _) => self.cursor.move_to(Direction::Up, &self.buffer),
+            (KeyCode::Down, _) => self.cursor.move_to(Direction::Down, &self.buffer),
+            (KeyCode::Left, _) => self.cursor.move_to(Direction::Left, &self.buffer),
+            (KeyCode::Right, _) => self.cursor.move_to(Direction::Right, &self.buffer),
+
+            (KeyCode::Char('c'), Modifiers::CTRL) => {
+                self.anchor = anchor;
+                if self.copy(selection).is_none() {
+                    self.set_status_message("Nothing to copy");
+                }
+            }
+            (KeyCode::Char('x'), Modifiers::CTRL) => {
+                self.cut(selection);
+                self.buffer.commit_edit(EditKind::Other, before, self.cursor);
+            }
+            (KeyCode::Char('v'), Modifiers::CTRL) => {
+                if let Some(selection) = selection {
+                    self.delete_region(selection);
+                }
+                self.import();
+                self.paste(0);
+                self.buffer.commit_edit(EditKind::Other, before, self.cursor);
+            }
+            (KeyCode::Char('v'), Modifiers::ALT) => {
+                self.cycle_paste(yank);
+                self.buffer.commit_edit(EditKind::Other, before, self.cursor);
+            }
+
+            (KeyCode::Char('z'), Modifiers::CTRL) => self.undo(),
+            (KeyCode::Char('y'), Modifiers::CTRL) => self.redo(),
+            (KeyCode::Char('z'), Modifiers::ALT) => self.travel(Travel::Steps(-1)),
+            (KeyCode::Char('y'), Modifiers::ALT) => self.travel(Travel::Steps(1)),
+            (KeyCode::Char('b'), Modifiers::ALT) => self.next_branch(),
+            (KeyCode::Char('t'), Modifiers::CTRL) => self.time_travel(),
+
+            // Tab indents the selected rows rather than replacing them, Shift-Tab outdents with or without a selection.
+            (KeyCode::Tab, Modifiers::SHIFT) => {
+                self.anchor = anchor;
+                self.indent(true);
+                self.buffer.commit_edit(EditKind::Other, before, self.cursor);
+            }
+            (KeyCode::Tab, Modifiers::NONE) if selection.is_some() => {
+                self.anchor = anchor;
+                self.indent(false);
+                self.buffer.commit_edit(EditKind::Other, before, self.cursor);
+            }
+            (KeyCode::Tab, Modifiers::NONE) => {
+                self.insert_char('\t');
+                self.buffer.commit_edit(EditKind::Insert, before, self.cursor);
+            }
+            (KeyCode::Char(ch), Modifiers::NONE) => {
+                // Typing over a selection replaces it, as a step of its own.
+                let kind = match selection {
+                    Some(selection) => {
+                        self.delete_region(selection);
+                        EditKind::Other
+                    }
+                    None => EditKind::Insert,
+                };
+                self.insert_char(ch);
+                self.buffer.commit_edit(kind, before, self.cursor);
+            }
+
+            // Escape drops the selection, and the matches of the last search.
+            (KeyCode::Esc, _) => self.set_search(None),
+
+            // Ctrl-L, function keys and sequences we don't know do nothing.
+            _ => {}
+        }
+
+        self.quit_times = QUIT_TIMES;
+        self.close_confirmed = false;
+        true
+    }
+
+    /// Draws to `terminal` and feeds its keypresses to the editor until the user quits.
+    ///
+    /// Any error from the terminal ends the session, including running out of scripted keys
+    /// on a [`VirtualTerminal`](crate::terminal::VirtualTerminal).
+    pub fn run<T: Terminal>(&mut self, terminal: &mut T) -> io::Result<()> {
+        loop {
+            let loading = self.poll_buffers();
+            terminal.write_frame(&self.refresh_screen())?;
+            let event = if loading {
+                match terminal.poll_event(Duration::from_millis(LOADING_REFRESH_MS))? {
+                    Some(event) => event,
+                    None => continue,
+                }
+            } else {
+                terminal.read_event()?
+            };
+            if !self.process_event(event) {
+                return terminal.write_frame(b"\x1b[2J\x1b[H");
+            }
+        }
+    }
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Returns a path in the temporary directory that no other test uses, with `contents` in it.
+    fn temp_file(name: &str, contents: &[u8]) -> String {
+        let path = std::env::temp_dir().join(format!("tiny-editor-editor-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    /// Returns an editor with `text` pasted into it, the cursor at its end.
+    fn editor_with(text: &str) -> Editor {
+        let mut editor = Editor::new(10, 60);
+        editor.process_event(Event::Paste(text.to_string()));
+        editor
+    }
+
+    fn type_str(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            editor.process_key(Key::char(c));
+            editor.refresh_screen();
+        }
+    }
+
+    #[test]
+    fn the_match_at_the_cursor_and_how_many_there_are() {
+        let mut editor = editor_with("ab ab\nab\nxab");
+        editor.process_key(Key::ctrl('f'));
+        type_str(&mut editor, "ab");
+        assert_eq!(editor.match_count(), Some((Some(1), 4)));
+        editor.process_key(Key::from(KeyCode::Right));
+        editor.process_key(Key::from(KeyCode::Enter));
+        editor.refresh_screen();
+        assert_eq!(editor.cursor(), Cursor::new(3, 0));
+        assert_eq!(editor.match_count(), Some((Some(2), 4)));
+        editor.process_key(Key::from(KeyCode::Right));
+        editor.refresh_screen();
+        assert_eq!(editor.match_count(), Some((None, 4)));
+        // Counted again after an edit.
+        editor.process_key(Key::from(KeyCode::Home));
+        type_str(&mut editor, "ab");
+        assert_eq!(editor.match_count(), Some((Some(2), 5)));
+        editor.process_key(Key::from(KeyCode::Esc));
+        editor.refresh_screen();
+        assert_eq!(editor.match_count(), None);
+    }
+
+    #[test]
+    fn big_texts_are_only_counted_once_the_search_is_confirmed() {
+        let rows = COUNT_MAX_LEN / 64 + 1;
+        let mut editor = editor_with(&format!("ab{}\n", "-".repeat(61)).repeat(rows));
+        editor.process_key(Key::ctrl('f'));
+        type_str(&mut editor, "ab");
+        assert_eq!(editor.match_count(), None);
+        editor.process_key(Key::from(KeyCode::Enter));
+        editor.refresh_screen();
+        assert_eq!(editor.match_count(), Some((Some(1), rows)));
+        type_str(&mut editor, "x");
+        assert_eq!(editor.match_count(), None);
+    }
+
+    #[test]
+    fn switching_between_buffers() {
+        let (a, b) = (temp_file("a.txt", b"alpha\n"), temp_file("b.txt", b"beta\n"));
+        let mut editor = Editor::new(10, 60);
+        editor.open(&a).unwrap();
+        editor.open(&b).unwrap();
+        // The empty buffer the editor started with is replaced by the first file.
+        assert_eq!((editor.buffer_count(), editor.current_buffer()), (2, 1));
+        editor.process_key(Key::new(KeyCode::End));
+
+        editor.process_key(Key::alt('1'));
+        assert_eq!((editor.current_buffer(), editor.cursor()), (0, Cursor::new(0, 0)));
+        assert_eq!(editor.buffer().row_chars(0).unwrap(), b"alpha");
+        // Each buffer keeps where its cursor was.
+        editor.process_key(Key::with(KeyCode::PageDown, Modifiers::CTRL));
+        assert_eq!((editor.current_buffer(), editor.cursor()), (1, Cursor::new(4, 0)));
+        editor.process_key(Key::with(KeyCode::PageDown, Modifiers::CTRL));
+        assert_eq!(editor.current_buffer(), 0);
+        editor.process_key(Key::with(KeyCode::PageUp, Modifiers::CTRL));
+        assert_eq!(editor.current_buffer(), 1);
+
+        editor.process_key(Key::ctrl('b'));
+        type_str(&mut editor, "a.txt");
+        editor.process_key(Key::new(KeyCode::Enter));
+        assert_eq!(editor.current_buffer(), 0);
+        editor.open(&b).unwrap();
+        assert_eq!((editor.buffer_count(), editor.current_buffer()), (2, 1));
+        editor.process_key(Key::alt('9'));
+        assert_eq!((editor.current_buffer(), editor.status_message()), (1, "There is no buffer 9"));
+        fs::remove_file(a).unwrap();
+        fs::remove_file(b).unwrap();
+    }
+
+    #[test]
+    fn closing_a_modified_buffer_takes_a_second_ctrl_w() {
+        let (a, b) = (temp_file("close-a.txt", b"alpha\n"), temp_file("close-b.txt", b"beta\n"));
+        let mut editor = Editor::new(10, 60);
+        editor.open(&a).unwrap();
+        editor.open(&b).unwrap();
+        type_str(&mut editor, "x");
+        assert!(editor.buffer_name(1).ends_with("close-b.txt*"));
+
+        editor.process_key(Key::ctrl('w'));
+        assert_eq!(editor.buffer_count(), 2);
+        assert!(editor.status_message().starts_with("WARNING!!!"));
+        // Any other key in between asks again.
+        editor.process_key(Key::new(KeyCode::Left));
+        editor.process_key(Key::ctrl('w'));
+        assert_eq!(editor.buffer_count(), 2);
+        editor.process_key(Key::ctrl('w'));
+        assert_eq!((editor.buffer_count(), editor.current_buffer()), (1, 0));
+        assert_eq!(editor.buffer().filename(), Some(a.as_str()));
+        // A clean buffer closes at once, and the last one leaves an empty buffer.
+        editor.process_key(Key::ctrl('w'));
+        assert_eq!(editor.buffer_count(), 1);
+        assert_eq!((editor.buffer().filename(), editor.buffer().numrows()), (None, 0));
+        fs::remove_file(a).unwrap();
+        fs::remove_file(b).unwrap();
+    }
+
+    #[test]
+    fn buffers_out_of_sight_go_on_loading() {
+        let big = temp_file("big.txt", "0123456\n".repeat((8 << 20) / 8 + 1000).as_bytes());
+        let small = temp_file("small.txt", b"small\n");
+        let mut editor = Editor::new(10, 60);
+        editor.open(&big).unwrap();
+        assert!(editor.buffer().is_loading());
+        editor.open(&small).unwrap();
+        while editor.poll_buffers() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(!editor.buffer_at(0).is_loading());
+        assert_eq!(editor.buffer_at(0).numrows(), (8 << 20) / 8 + 1000);
+        fs::remove_file(big).unwrap();
+        fs::remove_file(small).unwrap();
+    }
+
+    #[test]
+    fn moving_between_windows() {
+        let mut editor = Editor::new(24, 80);
+        editor.process_key(Key::alt('s'));
+        editor.process_key(Key::alt('S'));
+        // Window 0 on top, window 1 below it on the left and window 2 beside that.
+        assert_eq!((editor.window_count(), editor.focused_window()), (3, 2));
+        for (key, focused) in [('h', 1), ('h', 1), ('l', 2), ('k', 0), ('k', 0), ('j', 1), ('w', 2), ('w', 0), ('w', 1)] {
+            editor.process_key(Key::alt(key));
+            assert_eq!(editor.focused_window(), focused, "after Alt-{}", key);
+        }
+        editor.process_key(Key::alt('q'));
+        assert_eq!((editor.window_count(), editor.focused_window()), (2, 1));
+        editor.process_key(Key::alt('q'));
+        editor.process_key(Key::alt('q'));
+        assert_eq!((editor.window_count(), editor.status_message()), (1, "Can't close the only window"));
+    }
+
+    /// Selects a block from `at` down `rows` more rows, with no columns in it.
+    fn select_block(editor: &mut Editor, at: Cursor, rows: usize) {
+        editor.cursor = at;
+        for _ in 0..rows {
+            editor.process_key(Key::with(KeyCode::Down, Modifiers::ALT | Modifiers::SHIFT));
+        }
+    }
+
+    fn rows(editor: &Editor) -> Vec<String> {
+        let buffer = editor.buffer();
+        (0..buffer.numrows()).map(|cy| String::from_utf8(buffer.row_chars(cy).unwrap()).unwrap()).collect()
+    }
+
+    #[test]
+    fn typing_into_a_block() {
+        let mut editor = editor_with("abcd\nab\nabcd\n");
+        select_block(&mut editor, Cursor::new(3, 0), 2);
+        type_str(&mut editor, "XY");
+        // The short row is padded up to the block.
+        assert_eq!(rows(&editor), ["abcXYd", "ab XY", "abcXYd"]);
+        assert_eq!(editor.cursor(), Cursor::new(5, 2));
+        editor.process_key(Key::new(KeyCode::Backspace));
+        assert_eq!(rows(&editor), ["abcXd", "ab X", "abcXd"]);
+        editor.process_key(Key::new(KeyCode::Delete));
+        assert_eq!(rows(&editor), ["abcX", "ab X", "abcX"]);
+        // Deleting back and forth is one step of the undo history, as it is without a block.
+        editor.process_key(Key::ctrl('z'));
+        assert_eq!(rows(&editor), ["abcXYd", "ab XY", "abcXYd"]);
+    }
+
+    #[test]
+    fn typing_into_a_block_over_a_tab() {
+        let mut editor = editor_with("\tb\n1234567\tb\n");
+        // A column at screen column 4, in the middle of the first tab.
+        editor.block = Some(Block { anchor: (0, 4), corner: (1, 4) });
+        type_str(&mut editor, "X");
+        // The tab is split in two at the column rather than typed after.
+        assert_eq!(rows(&editor), ["    X    b", "1234X567\tb"]);
+        assert_eq!(editor.cursor(), Cursor::new(5, 1));
+        editor.process_key(Key::ctrl('z'));
+        assert_eq!(rows(&editor), ["\tb", "1234567\tb"]);
+    }
+
+    #[test]
+    fn pasting_into_a_block() {
+        let mut editor = editor_with("ab\nab\n");
+        select_block(&mut editor, Cursor::new(1, 0), 1);
+        editor.process_event(Event::Paste("xy".to_string()));
+        assert_eq!(rows(&editor), ["axyb", "axyb"]);
+        assert_eq!(editor.block().map(|block| block.columns()), Some(3..3));
+        editor.process_key(Key::ctrl('z'));
+        assert_eq!(rows(&editor), ["ab", "ab"]);
+
+        // A paste of several lines goes in at the cursor, and the block is gone.
+        select_block(&mut editor, Cursor::new(1, 0), 1);
+        editor.process_event(Event::Paste("1\n2".to_string()));
+        assert_eq!(rows(&editor), ["ab", "a1", "2b"]);
+        assert!(editor.block().is_none());
+    }
+
+    #[test]
+    fn pasting_takes_back_a_first_ctrl_w() {
+        let mut editor = editor_with("unsaved");
+        editor.process_key(Key::ctrl('w'));
+        assert!(editor.status_message().starts_with("WARNING!!!"));
+        editor.process_event(Event::Paste("!".to_string()));
+        editor.process_key(Key::ctrl('w'));
+        assert_eq!(rows(&editor), ["unsaved!"]);
+    }
+
+    #[test]
+    fn a_paste_is_one_step_of_the_undo_history() {
+        let mut editor = editor_with("");
+        type_str(&mut editor, "ab");
+        editor.process_key(Key::new(KeyCode::Left));
+        editor.process_event(Event::Paste("1\n2\n3".to_string()));
+        editor.process_event(Event::Paste("c".to_string()));
+        assert_eq!(rows(&editor), ["a1", "2", "3cb"]);
+        editor.process_key(Key::ctrl('z'));
+        assert_eq!(rows(&editor), ["a1", "2", "3b"]);
+        assert_eq!(editor.cursor(), Cursor::new(1, 2));
+        editor.process_key(Key::ctrl('z'));
+        assert_eq!(rows(&editor), ["ab"]);
+        assert_eq!(editor.cursor(), Cursor::new(1, 0));
+        editor.process_key(Key::ctrl('y'));
+        assert_eq!(rows(&editor), ["a1", "2", "3b"]);
+
+        // A paste over a selection takes its place in the same step.
+        editor.process_key(Key::with(KeyCode::Up, Modifiers::SHIFT));
+        editor.process_event(Event::Paste("x".to_string()));
+        assert_eq!(rows(&editor), ["a1", "2xb"]);
+        editor.process_key(Key::ctrl('z'));
+        assert_eq!(rows(&editor), ["a1", "2", "3b"]);
+    }
+
+    fn cursors_at(editor: &Editor) -> Vec<(usize, usize)> {
+        editor.cursors().iter().map(|cursor| (cursor.cx, cursor.cy)).collect()
+    }
+
+    #[test]
+    fn typing_and_deleting_at_cursors_on_one_row() {
+        let mut editor = editor_with("foo foo foo\n");
+        editor.cursor = Cursor::new(1, 0);
+        editor.process_key(Key::ctrl('d'));
+        editor.process_key(Key::ctrl('d'));
+        assert_eq!(cursors_at(&editor), [(1, 0), (5, 0), (9, 0)]);
+        // Each edit moves the cursors after it on the same row.
+        type_str(&mut editor, "XY");
+        assert_eq!(rows(&editor), ["fXYoo fXYoo fXYoo"]);
+        assert_eq!(cursors_at(&editor), [(3, 0), (9, 0), (15, 0)]);
+        for _ in 0..3 {
+            editor.process_key(Key::new(KeyCode::Backspace));
+        }
+        assert_eq!(rows(&editor), ["oo oo oo"]);
+        editor.process_key(Key::new(KeyCode::Backspace));
+        assert_eq!(rows(&editor), ["oooooo"]);
+        assert_eq!(cursors_at(&editor), [(0, 0), (2, 0), (4, 0)]);
+        // Cursors that meet become one.
+        editor.process_key(Key::new(KeyCode::Left));
+        editor.process_key(Key::new(KeyCode::Left));
+        assert_eq!(cursors_at(&editor), [(0, 0), (2, 0)]);
+        editor.process_key(Key::new(KeyCode::Delete));
+        assert_eq!((rows(&editor), cursors_at(&editor)), (vec!["oooo".to_string()], vec![(0, 0), (1, 0)]));
+        // Deleting at every cursor is one step of the undo history, and so is typing.
+        editor.process_key(Key::ctrl('z'));
+        assert_eq!(rows(&editor), ["fXYoo fXYoo fXYoo"]);
+    }
+
+    #[test]
+    fn typing_and_deleting_at_cursors_on_rows_next_to_each_other() {
+        let mut editor = editor_with("ab\ncd\nef\ngh\n");
+        editor.cursor = Cursor::new(1, 0);
+        editor.process_key(Key::with(KeyCode::Down, Modifiers::ALT));
+        editor.process_key(Key::with(KeyCode::Down, Modifiers::ALT));
+        assert_eq!(cursors_at(&editor), [(1, 0), (1, 1), (1, 2)]);
+        editor.process_key(Key::new(KeyCode::Enter));
+        assert_eq!(rows(&editor), ["a", "b", "c", "d", "e", "f", "gh"]);
+        assert_eq!(cursors_at(&editor), [(0, 1), (0, 3), (0, 5)]);
+        editor.process_key(Key::new(KeyCode::Backspace));
+        assert_eq!(rows(&editor), ["ab", "cd", "ef", "gh"]);
+        assert_eq!(cursors_at(&editor), [(1, 0), (1, 1), (1, 2)]);
+        // Joining the rows brings the cursors onto one row.
+        editor.process_key(Key::new(KeyCode::End));
+        editor.process_key(Key::new(KeyCode::Delete));
+        assert_eq!(rows(&editor), ["abcdefgh"]);
+        assert_eq!(cursors_at(&editor), [(2, 0), (4, 0), (6, 0)]);
+        // Backspace at the start of a row takes the cursor to the end of the row above, and
+        // cursors on the same spot become one.
+        let mut editor = editor_with("a\n\n\n");
+        editor.cursor = Cursor::new(0, 1);
+        editor.process_key(Key::with(KeyCode::Down, Modifiers::ALT));
+        editor.process_key(Key::new(KeyCode::Backspace));
+        assert_eq!((rows(&editor), cursors_at(&editor)), (vec!["a".to_string()], vec![(1, 0)]));
+    }
+
+    #[test]
+    fn up_and_down_bring_back_earlier_answers() {
+        let mut editor = Editor::new(10, 60);
+        for answer in ["one", "two"] {
+            editor.process_key(Key::ctrl('b'));
+            type_str(&mut editor, answer);
+            editor.process_key(Key::new(KeyCode::Enter));
+        }
+        editor.process_key(Key::ctrl('b'));
+        type_str(&mut editor, "thr");
+        let buf = |editor: &Editor| editor.prompt.as_ref().unwrap().buf.clone();
+        for (code, expected) in [
+            (KeyCode::Up, "two"),
+            (KeyCode::Up, "one"),
+            (KeyCode::Up, "one"),
+            (KeyCode::Down, "two"),
+            (KeyCode::Down, "thr"), // what was typed before going back
+            (KeyCode::Down, "thr"),
+        ] {
+            editor.process_key(Key::new(code));
+            assert_eq!(buf(&editor), expected);
+        }
+        editor.process_key(Key::new(KeyCode::Esc));
+
+        // Each kind of prompt has a history of its own.
+        editor.process_key(Key::ctrl('f'));
+        editor.process_key(Key::new(KeyCode::Up));
+        assert_eq!(buf(&editor), "");
+    }
+
+    fn mouse(kind: MouseKind, column: usize, row: usize) -> Event {
+        Event::Mouse(MouseEvent { kind, column, row, modifiers: Modifiers::NONE })
+    }
+
+    #[test]
+    fn selecting_with_shift() {
+        let mut editor = editor_with("hello world\nsecond\n");
+        editor.cursor = Cursor::new(0, 0);
+        for _ in 0..5 {
+            editor.process_key(Key::with(KeyCode::Right, Modifiers::SHIFT));
+        }
+        assert_eq!(editor.selection(), Some((Cursor::new(0, 0), Cursor::new(5, 0))));
+        editor.process_key(Key::with(KeyCode::Down, Modifiers::SHIFT));
+        assert_eq!(editor.selection(), Some((Cursor::new(0, 0), Cursor::new(5, 1))));
+        // Back where it started, nothing is selected.
+        editor.process_key(Key::with(KeyCode::Up, Modifiers::SHIFT));
+        editor.process_key(Key::with(KeyCode::Home, Modifiers::SHIFT));
+        assert_eq!(editor.selection(), None);
+        editor.process_key(Key::with(KeyCode::End, Modifiers::SHIFT));
+        editor.process_key(Key::with(KeyCode::Left, Modifiers::SHIFT));
+        assert_eq!(editor.selection(), Some((Cursor::new(0, 0), Cursor::new(10, 0))));
+
+        // Typing replaces the selection, and moving without Shift drops it.
+        type_str(&mut editor, "X");
+        assert_eq!(rows(&editor), ["Xd", "second"]);
+        editor.process_key(Key::with(KeyCode::Right, Modifiers::SHIFT));
+        editor.process_key(Key::new(KeyCode::Right));
+        assert_eq!((editor.selection(), editor.cursor()), (None, Cursor::new(0, 1)));
+    }
+
+    #[test]
+    fn selecting_with_the_mouse() {
+        let mut editor = editor_with("hello world\nsecond\nthird\n");
+        editor.process_event(mouse(MouseKind::Press(MouseButton::Left), 6, 0));
+        editor.process_event(mouse(MouseKind::Drag(MouseButton::Left), 4, 1));
+        editor.process_event(mouse(MouseKind::Drag(MouseButton::Left), 3, 2));
+        editor.process_event(mouse(MouseKind::Release(MouseButton::Left), 3, 2));
+        assert_eq!(editor.selection(), Some((Cursor::new(6, 0), Cursor::new(3, 2))));
+        editor.process_key(Key::ctrl('x'));
+        assert_eq!(rows(&editor), ["hello rd"]);
+
+        // A click without a drag selects nothing.
+        editor.process_event(mouse(MouseKind::Press(MouseButton::Left), 2, 0));
+        editor.process_event(mouse(MouseKind::Release(MouseButton::Left), 2, 0));
+        assert_eq!((editor.selection(), editor.cursor()), (None, Cursor::new(2, 0)));
+    }
+
+    #[test]
+    fn clicking_on_tabs_and_wide_characters() {
+        let mut editor = editor_with("\tab\n漢字x\n");
+        // The blanks of a tab, and the second column of a wide character, are the character itself;
+        // `cx` counts bytes, three to each of these.
+        for ((column, row), cx) in [((3, 0), 0), ((8, 0), 1), ((9, 0), 2), ((1, 1), 0), ((2, 1), 3), ((4, 1), 6), ((30, 1), 7)] {
+            editor.process_event(mouse(MouseKind::Press(MouseButton::Left), column, row));
+            editor.process_event(mouse(MouseKind::Release(MouseButton::Left), column, row));
+            assert_eq!(editor.cursor(), Cursor::new(cx, row), "column {}", column);
+        }
+    }
+
+    #[test]
+    fn dragging_past_the_top_of_the_window_scrolls_up() {
+        let mut editor = editor_with(&"line\n".repeat(30));
+        editor.set_tab_line(true);
+        editor.cursor = Cursor::new(0, 20);
+        editor.refresh_screen();
+        let rowoff = editor.rowoff;
+        editor.process_event(mouse(MouseKind::Press(MouseButton::Left), 2, 1));
+        assert_eq!(editor.cursor(), Cursor::new(2, rowoff));
+        // Onto the tab line, one row at a time.
+        for up in 1..=2 {
+            editor.process_event(mouse(MouseKind::Drag(MouseButton::Left), 1, 0));
+            editor.refresh_screen();
+            assert_eq!((editor.rowoff, editor.cursor()), (rowoff - up, Cursor::new(1, rowoff - up)));
+        }
+        editor.process_event(mouse(MouseKind::Release(MouseButton::Left), 1, 0));
+        assert_eq!(editor.selection(), Some((Cursor::new(1, rowoff - 2), Cursor::new(2, rowoff))));
+    }
+
+    #[test]
+    fn indenting_and_outdenting_the_selected_rows() {
+        let mut editor = editor_with("a\n\n  b\nc\n");
+        editor.cursor = Cursor::new(1, 0);
+        editor.process_key(Key::with(KeyCode::Down, Modifiers::SHIFT));
+        editor.process_key(Key::with(KeyCode::Down, Modifiers::SHIFT));
+        editor.process_key(Key::with(KeyCode::Down, Modifiers::SHIFT));
+        editor.process_key(Key::with(KeyCode::Home, Modifiers::SHIFT));
+        // The row the selection ends at the start of isn't indented, and neither is an empty row.
+        editor.process_key(Key::new(KeyCode::Tab));
+        assert_eq!(rows(&editor), ["\ta", "", "\t  b", "c"]);
+        assert_eq!(editor.selection(), Some((Cursor::new(2, 0), Cursor::new(0, 3))));
+        editor.process_key(Key::with(KeyCode::Tab, Modifiers::SHIFT));
+        editor.process_key(Key::with(KeyCode::Tab, Modifiers::SHIFT));
+        assert_eq!(rows(&editor), ["a", "", "b", "c"]);
+        assert_eq!(editor.selection(), Some((Cursor::new(1, 0), Cursor::new(0, 3))));
+        // Each is one step of the undo history.
+        editor.process_key(Key::ctrl('z'));
+        assert_eq!(rows(&editor), ["a", "", "  b", "c"]);
+    }
+
+    #[test]
+    fn pasting_older_entries_of_the_kill_ring() {
+        let mut editor = editor_with("one two\nend\n");
+        editor.cursor = Cursor::new(0, 0);
+        for _ in 0..2 {
+            for _ in 0..3 {
+                editor.process_key(Key::with(KeyCode::Right, Modifiers::SHIFT));
+            }
+            editor.process_key(Key::ctrl('c'));
+            editor.process_key(Key::new(KeyCode::Right));
+        }
+        editor.cursor = Cursor::new(3, 1);
+        editor.process_key(Key::ctrl('v'));
+        assert_eq!(rows(&editor), ["one two", "endtwo"]);
+        editor.process_key(Key::alt('v'));
+        assert_eq!(rows(&editor), ["one two", "endone"]);
+        assert_eq!(editor.status_message(), "Pasted entry 2 of 2");
+        // Round the ring back to the newest.
+        editor.process_key(Key::alt('v'));
+        assert_eq!(rows(&editor), ["one two", "endtwo"]);
+        assert_eq!(editor.cursor(), Cursor::new(6, 1));
+        editor.process_key(Key::new(KeyCode::Left));
+        editor.process_key(Key::alt('v'));
+        assert_eq!(rows(&editor), ["one two", "endtwo"]);
+        assert_eq!(editor.status_message(), "Paste with Ctrl-V first");
+
+        // Copied without a selection, the whole row is pasted above the cursor's.
+        editor.process_key(Key::ctrl('c'));
+        editor.cursor = Cursor::new(2, 0);
+        editor.process_key(Key::ctrl('v'));
+        assert_eq!(rows(&editor), ["endtwo", "one two", "endtwo"]);
+        assert_eq!(editor.cursor(), Cursor::new(0, 1));
+        editor.process_key(Key::alt('v'));
+        assert_eq!(rows(&editor), ["twoone two", "endtwo"]);
+    }
+}
@@ -0,0 +1,112 @@
+//! Putting the terminal back the way we found it, the equivalent of `atexit(disableRawMode)` in C.
+//!
+//! A crash in raw mode leaves the shell without echo and stuck on the alternate screen,
+//! so besides restoring on drop, [`TerminalGuard`] also restores from a panic hook
+//! (before the panic message is printed) and when the process gets SIGTERM or SIGHUP, or
+//! SIGBUS from reading a mapped file that another program truncated.
+
+use std::io;
+use std::mem::MaybeUninit;
+use std::panic;
+use std::process;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Once, OnceLock};
+use std::thread;
+
+use signal_hook::consts::{SIGHUP, SIGTERM};
+use signal_hook::iterator::Signals;
+
+static ORIGINAL_TERMIOS: OnceLock<libc::termios> = OnceLock::new(); // terminal attributes before raw mode
+static ACTIVE: AtomicBool = AtomicBool::new(false); // true while there is something to restore
+static INSTALL: Once = Once::new();
+
+/// Restores the terminal when dropped. Create it before entering raw mode.
+pub struct TerminalGuard {
+    _private: (),
+}
+
+impl TerminalGuard {
+    /// Saves the current terminal attributes and installs the panic hook and signal handlers.
+    pub fn new() -> io::Result<TerminalGuard> {
+        if ORIGINAL_TERMIOS.get().is_none() {
+            let mut termios = MaybeUninit::uninit();
+            // termion switches the terminal on stdout into raw mode, so that is the one we save.
+            if unsafe { libc::tcgetattr(libc::STDOUT_FILENO, termios.as_mut_ptr()) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            let _ = ORIGINAL_TERMIOS.set(unsafe { termios.assume_init() });
+        }
+
+        let mut result = Ok(());
+        INSTALL.call_once(|| {
+            install_panic_hook();
+            result = install_signal_handlers();
+        });
+        result?;
+
+        ACTIVE.store(true, Ordering::SeqCst);
+        Ok(TerminalGuard { _private: () })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Restores cooked mode, shows the cursor, turns off mouse reporting and bracketed paste,
+/// and leaves the alternate screen.
+///
+/// Only the first call after a [`TerminalGuard`] is created does anything. It writes with
+/// `write(2)` rather than through `io::stdout()`, whose lock may be held by the thread that panicked.
+pub fn restore() {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    if let Some(termios) = ORIGINAL_TERMIOS.get() {
+        unsafe { libc::tcsetattr(libc::STDOUT_FILENO, libc::TCSAFLUSH, termios) };
+    }
+
+    // disable mouse reporting and bracketed paste, show cursor, leave alternate screen
+    let seq = b"\x1b[?1006l\x1b[?1002l\x1b[?2004l\x1b[?25h\x1b[?1049l";
+    unsafe { libc::write(libc::STDOUT_FILENO, seq.as_ptr().cast(), seq.len()) };
+}
+
+fn install_panic_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore(); // so the message is printed to a terminal that can show it
+        previous(info);
+    }));
+}
+
+fn install_signal_handlers() -> io::Result<()> {
+    let mut signals = Signals::new([SIGTERM, SIGHUP])?;
+    thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            restore();
+            process::exit(128 + signal); // the exit status shells use for "killed by signal"
+        }
+    });
+    install_sigbus_handler()
+}
+
+/// SIGBUS comes from the very read that hit the missing part of a mapped file, so it can't wait
+/// for another thread like the signals above. The handler restores the terminal right there and
+/// returns with the default action back in place, so the read faults again and the process dies
+/// of SIGBUS as it would have. Everything `restore` does is safe in a signal handler.
+fn install_sigbus_handler() -> io::Result<()> {
+    extern "C" fn on_sigbus(_: libc::c_int) {
+        restore();
+    }
+    let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+    action.sa_sigaction = on_sigbus as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    action.sa_flags = libc::SA_RESETHAND;
+    if unsafe { libc::sigaction(libc::SIGBUS, &action, ptr::null_mut()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
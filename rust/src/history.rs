@@ -0,0 +1,624 @@
+//! Undo and redo.
+//!
+//! Every change to a [`Buffer`](crate::Buffer) is recorded as an [`Edit`] of its text. The edits
+//! made by one command (a keypress, a paste) are committed together as a step, and a run of
+//! typing or deleting in one place keeps adding to the same step, so it is undone in one go.
+//!
+//! Steps form a tree rather than a stack: making a change after undoing starts a new branch
+//! next to the steps that were undone instead of throwing them away. Every state of the buffer is
+//! a node of the tree, numbered in the order the states were made, so walking the numbers up and
+//! down visits every state ever reached, whatever branch it is on, the way Vim's `g-` and `g+` do.
+//!
+//! A history can also be written to a file and read back in a later session, see [`History::encode`].
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cursor::Cursor;
+
+/// Starts every undo file, followed by the version of the format.
+const MAGIC: &[u8] = b"tiny-editor undo\n\x02";
+
+/// The bytes at `at` that were `deleted` and the ones `inserted` in their place.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edit {
+    pub at: usize,
+    pub deleted: Vec<u8>,
+    pub inserted: Vec<u8>,
+}
+
+/// What kind of command made a step, which decides whether the next one can join it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditKind {
+    Insert, // typing, which runs together
+    Delete, // deleting one character at a time, which runs together too
+    Other, // everything else, which is always a step of its own
+}
+
+/// Edits that are undone and redone together, with where the cursor was before and after them.
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub kind: EditKind,
+    pub edits: Vec<Edit>,
+    pub before: Cursor,
+    pub after: Cursor,
+}
+
+/// A state of the buffer, reached from its parent by applying `step`.
+#[derive(Debug)]
+struct Node {
+    step: Step, // no edits for the root
+    parent: usize, // 0 for the root too
+    children: Vec<usize>, // oldest first
+    redo: Option<usize>, // the child redo goes to: the one last made or last undone
+    time: u64, // seconds since the epoch when the state was last changed
+}
+
+/// One step of the way from a state of the tree to another, see [`History::go_to`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Move {
+    Revert(usize), // revert the step of this node, ending up in its parent
+    Apply(usize), // apply the step of this node, ending up in it
+}
+
+/// How far to go back or forward in the history, in steps or in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Travel {
+    Steps(isize),
+    Seconds(i64),
+}
+
+impl Travel {
+    /// Parses `earlier N` or `later N`, where `N` is a number of steps, or a time when followed by
+    /// `s`, `m`, `h` or `d`, as in `earlier 5m`.
+    pub fn parse(s: &str) -> Option<Travel> {
+        let mut words = s.split_whitespace();
+        let sign = match words.next()? {
+            "earlier" => -1,
+            "later" => 1,
+            _ => return None,
+        };
+        let amount = words.next()?;
+        if words.next().is_some() {
+            return None;
+        }
+        let digits = amount.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let n: i64 = digits.parse().ok()?;
+        let seconds = match &amount[digits.len()..] {
+            "" => return Some(Travel::Steps(sign as isize * isize::try_from(n).ok()?)),
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => return None,
+        };
+        Some(Travel::Seconds(sign * n.checked_mul(seconds)?))
+    }
+}
+
+#[derive(Debug)]
+pub struct History {
+    nodes: Vec<Node>, // the root first, then every state in the order it was made
+    current: usize,
+    pending: Vec<Edit>, // edits made since the last commit
+    sealed: bool, // whether the current step is closed to more edits
+}
+
+impl Default for History {
+    fn default() -> History {
+        History {
+            nodes: vec![Node {
+                step: Step {
+                    kind: EditKind::Other,
+                    edits: Vec::new(),
+                    before: Cursor::default(),
+                    after: Cursor::default(),
+                },
+                parent: 0,
+                children: Vec::new(),
+                redo: None,
+                time: now(),
+            }],
+            current: 0,
+            pending: Vec::new(),
+            sealed: false,
+        }
+    }
+}
+
+impl History {
+    pub(crate) fn record(&mut self, edit: Edit) {
+        self.pending.push(edit);
+    }
+
+    /// Whether there are recorded edits that haven't been committed to a step yet.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Turns the edits recorded since the last commit into a step, or adds them to the current step
+    /// if they continue it: same kind of typing or deleting, starting where the cursor was left.
+    pub(crate) fn commit(&mut self, kind: EditKind, before: Cursor, after: Cursor) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let edits = std::mem::take(&mut self.pending);
+        let current = &mut self.nodes[self.current];
+        let step = &mut current.step;
+        if self.current != 0
+            && !self.sealed
+            && current.children.is_empty()
+            && kind != EditKind::Other
+            && step.kind == kind
+            && step.after == before
+        {
+            step.edits.extend(edits);
+            step.after = after;
+            current.time = now();
+            return;
+        }
+
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            step: Step {
+                kind,
+                edits,
+                before,
+                after,
+            },
+            parent: self.current,
+            children: Vec::new(),
+            redo: None,
+            time: now(),
+        });
+        let parent = &mut self.nodes[self.current];
+        parent.children.push(id);
+        parent.redo = Some(id);
+        self.current = id;
+        self.sealed = false;
+    }
+
+    /// Makes the next commit start a new step.
+    pub(crate) fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    /// Identifies the state the buffer is in, 0 being the one the history started from.
+    /// States are never reused, so the same state means the same text.
+    pub fn state(&self) -> usize {
+        self.current
+    }
+
+    /// Returns the number of the newest state, so states go from 0 to this.
+    pub fn last_state(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// Returns which of the branches leaving the current state redo follows, counting from 1,
+    /// and how many there are, or `None` if there is at most one.
+    pub fn branch(&self) -> Option<(usize, usize)> {
+        let node = &self.nodes[self.current];
+        if node.children.len() < 2 {
+            return None;
+        }
+        let redo = node.redo?;
+        let index = node.children.iter().position(|&child| child == redo)?;
+        Some((index + 1, node.children.len()))
+    }
+
+    /// Makes redo follow the next branch leaving the current state, going back to the first
+    /// after the last. Returns the branch it follows now, like [`History::branch`].
+    pub(crate) fn next_branch(&mut self) -> Option<(usize, usize)> {
+        let (index, count) = self.branch()?;
+        let node = &mut self.nodes[self.current];
+        node.redo = Some(node.children[index % count]);
+        self.branch()
+    }
+
+    pub(crate) fn step(&self, node: usize) -> &Step {
+        &self.nodes[node].step
+    }
+
+    /// Returns the state undo goes back to.
+    pub fn undo_target(&self) -> Option<usize> {
+        match self.current {
+            0 => None,
+            current => Some(self.nodes[current].parent),
+        }
+    }
+
+    /// Returns the state redo goes forward to.
+    pub fn redo_target(&self) -> Option<usize> {
+        self.nodes[self.current].redo
+    }
+
+    /// Returns the state `travel` goes to from the current one.
+    ///
+    /// Going by steps walks the states in the order they were made. Going by time lands on the
+    /// newest state made by then, counting from when the current one was made. A state is taken
+    /// as made no earlier than the ones before it, in case the clock was set back in between.
+    pub fn travel_target(&self, travel: Travel) -> usize {
+        match travel {
+            Travel::Steps(steps) => self.current.saturating_add_signed(steps).min(self.last_state()),
+            Travel::Seconds(seconds) => {
+                let made: Vec<u64> = self
+                    .nodes
+                    .iter()
+                    .scan(0, |latest, node| {
+                        *latest = node.time.max(*latest);
+                        Some(*latest)
+                    })
+                    .collect();
+                let time = made[self.current].saturating_add_signed(seconds);
+                made.partition_point(|&made| made <= time).saturating_sub(1)
+            }
+        }
+    }
+
+    /// Moves to state `target` and returns the steps to revert and apply on the way, in order:
+    /// back up to where the branches of the two states meet, then down to `target`.
+    /// Redo is left pointing down the branches just left, so it can go back the same way.
+    pub(crate) fn go_to(&mut self, target: usize) -> Vec<Move> {
+        let mut on_target_path = vec![false; self.nodes.len()];
+        let mut node = target;
+        while node != 0 {
+            on_target_path[node] = true;
+            node = self.nodes[node].parent;
+        }
+
+        let mut moves = Vec::new();
+        let mut node = self.current;
+        while node != 0 && !on_target_path[node] {
+            moves.push(Move::Revert(node));
+            node = self.nodes[node].parent;
+        }
+        let common = node;
+        let mut applies = Vec::new();
+        let mut node = target;
+        while node != common {
+            applies.push(Move::Apply(node));
+            node = self.nodes[node].parent;
+        }
+        moves.extend(applies.into_iter().rev());
+
+        for &(Move::Revert(node) | Move::Apply(node)) in &moves {
+            let parent = self.nodes[node].parent;
+            self.nodes[parent].redo = Some(node);
+        }
+        self.current = target;
+        self.sealed = true;
+        moves
+    }
+
+    /*** persistence ***/
+
+    /// Writes the history of the file at `path` to bytes, to be read back by [`History::decode`]
+    /// as long as the file still has contents that hash to `hash`.
+    pub fn encode(&self, path: &str, hash: u64) -> Vec<u8> {
+        let mut buf = MAGIC.to_vec();
+        put_bytes(&mut buf, path.as_bytes());
+        put_u64(&mut buf, hash);
+        put_u64(&mut buf, self.current as u64);
+        put_u64(&mut buf, self.nodes.len() as u64);
+        for node in &self.nodes {
+            put_u64(&mut buf, node.parent as u64);
+            put_u64(&mut buf, node.redo.map_or(0, |redo| redo as u64 + 1));
+            put_u64(&mut buf, node.time);
+            put_step(&mut buf, &node.step);
+        }
+        buf
+    }
+
+    /// Reads a history written by [`History::encode`]. Returns `None` if it is damaged,
+    /// or if it was written for another file or for other contents of this one.
+    pub fn decode(bytes: &[u8], path: &str, hash: u64) -> Option<History> {
+        let mut reader = Reader {
+            bytes: bytes.strip_prefix(MAGIC)?,
+        };
+        if reader.bytes()? != path.as_bytes() || reader.u64()? != hash {
+            return None;
+        }
+        let current = reader.usize()?;
+        let count = reader.usize()?;
+        let mut nodes: Vec<Node> = Vec::new();
+        for id in 0..count {
+            let parent = reader.usize()?;
+            let redo = reader.usize()?.checked_sub(1);
+            let time = reader.u64()?;
+            let step = reader.step()?;
+            // Parents come before their children, which is also what keeps the tree free of cycles.
+            if id > 0 && parent >= id {
+                return None;
+            }
+            if id > 0 {
+                nodes[parent].children.push(id);
+            }
+            nodes.push(Node {
+                step,
+                parent,
+                children: Vec::new(),
+                redo,
+                time,
+            });
+        }
+        let redo_is_child = |node: &Node| node.redo.is_none_or(|redo| node.children.contains(&redo));
+        if nodes.is_empty() || current >= nodes.len() || !nodes.iter().all(redo_is_child) || !reader.bytes.is_empty() {
+            return None;
+        }
+        Some(History {
+            nodes,
+            current,
+            pending: Vec::new(),
+            sealed: true,
+        })
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+}
+
+/// Hashes file contents with 64 bit FNV-1a, to tell whether a file changed since its history was written.
+/// Unlike `DefaultHasher` it gives the same result from one build to the next.
+pub fn content_hash<'a, I: IntoIterator<Item = &'a [u8]>>(chunks: I) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for chunk in chunks {
+        for &b in chunk {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+fn put_u64(buf: &mut Vec<u8>, n: u64) {
+    buf.extend_from_slice(&n.to_le_bytes());
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    put_u64(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn put_cursor(buf: &mut Vec<u8>, cursor: Cursor) {
+    put_u64(buf, cursor.cx as u64);
+    put_u64(buf, cursor.cy as u64);
+}
+
+fn put_step(buf: &mut Vec<u8>, step: &Step) {
+    buf.push(match step.kind {
+        EditKind::Insert => 0,
+        EditKind::Delete => 1,
+        EditKind::Other => 2,
+    });
+    put_cursor(buf, step.before);
+    put_cursor(buf, step.after);
+    put_u64(buf, step.edits.len() as u64);
+    for edit in &step.edits {
+        put_u64(buf, edit.at as u64);
+        put_bytes(buf, &edit.deleted);
+        put_bytes(buf, &edit.inserted);
+    }
+}
+
+/// Reads back what the `put_*` functions wrote, returning `None` past the end of the bytes.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.bytes.len() {
+            return None;
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn usize(&mut self) -> Option<usize> {
+        usize::try_from(self.u64()?).ok()
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.usize()?;
+        self.take(len)
+    }
+
+    fn cursor(&mut self) -> Option<Cursor> {
+        Some(Cursor::new(self.usize()?, self.usize()?))
+    }
+
+    fn step(&mut self) -> Option<Step> {
+        let kind = match self.take(1)?[0] {
+            0 => EditKind::Insert,
+            1 => EditKind::Delete,
+            2 => EditKind::Other,
+            _ => return None,
+        };
+        let (before, after) = (self.cursor()?, self.cursor()?);
+        let count = self.usize()?;
+        let mut edits = Vec::new();
+        for _ in 0..count {
+            edits.push(Edit {
+                at: self.usize()?,
+                deleted: self.bytes()?.to_vec(),
+                inserted: self.bytes()?.to_vec(),
+            });
+        }
+        Some(Step {
+            kind,
+            edits,
+            before,
+            after,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "/home/user/notes.txt";
+    const HASH: u64 = 0x1234_5678_9abc_def0;
+
+    fn edit(at: usize, deleted: &[u8], inserted: &[u8]) -> Edit {
+        Edit {
+            at,
+            deleted: deleted.to_vec(),
+            inserted: inserted.to_vec(),
+        }
+    }
+
+    /// A history with two branches off the first step, on the second of which it ends.
+    fn branching() -> History {
+        let mut history = History::default();
+        history.record(edit(0, b"", b"hello"));
+        history.commit(EditKind::Insert, Cursor::new(0, 0), Cursor::new(5, 0));
+        history.record(edit(5, b"", b"\n"));
+        history.commit(EditKind::Other, Cursor::new(5, 0), Cursor::new(0, 1));
+        history.go_to(1);
+        history.record(edit(0, b"h", b"J"));
+        history.commit(EditKind::Other, Cursor::new(1, 0), Cursor::new(1, 0));
+        history.go_to(0);
+        history
+    }
+
+    /// Writes a history the way [`History::encode`] does, from the parent and `redo` of each node.
+    fn encode_nodes(current: u64, nodes: &[(u64, u64)]) -> Vec<u8> {
+        let mut buf = MAGIC.to_vec();
+        put_bytes(&mut buf, PATH.as_bytes());
+        put_u64(&mut buf, HASH);
+        put_u64(&mut buf, current);
+        put_u64(&mut buf, nodes.len() as u64);
+        for &(parent, redo) in nodes {
+            put_u64(&mut buf, parent);
+            put_u64(&mut buf, redo);
+            put_u64(&mut buf, 0);
+            put_step(&mut buf, &History::default().nodes[0].step);
+        }
+        buf
+    }
+
+    #[test]
+    fn round_trip() {
+        let history = branching();
+        let bytes = history.encode(PATH, HASH);
+        let decoded = History::decode(&bytes, PATH, HASH).unwrap();
+        assert_eq!(decoded.encode(PATH, HASH), bytes);
+        assert_eq!(decoded.state(), 0);
+        assert_eq!(decoded.last_state(), 3);
+        assert_eq!(decoded.redo_target(), Some(1));
+        assert_eq!(decoded.step(3).edits, vec![edit(0, b"h", b"J")]);
+        assert_eq!(decoded.step(1).after, Cursor::new(5, 0));
+
+        let mut decoded = decoded;
+        decoded.go_to(1);
+        assert_eq!(decoded.branch(), Some((2, 2)), "redo keeps following the branch last left");
+
+        let empty = History::default();
+        assert!(History::decode(&empty.encode(PATH, HASH), PATH, HASH).is_some());
+    }
+
+    #[test]
+    fn rejects_another_file_or_other_contents() {
+        let bytes = branching().encode(PATH, HASH);
+        assert!(History::decode(&bytes, "/home/user/other.txt", HASH).is_none());
+        assert!(History::decode(&bytes, PATH, HASH + 1).is_none());
+        assert!(History::decode(&bytes[1..], PATH, HASH).is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_or_extended_bytes() {
+        let bytes = branching().encode(PATH, HASH);
+        for len in 0..bytes.len() {
+            assert!(History::decode(&bytes[..len], PATH, HASH).is_none(), "cut at {}", len);
+        }
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(History::decode(&longer, PATH, HASH).is_none());
+    }
+
+    #[test]
+    fn rejects_indices_out_of_range() {
+        // (parent, redo + 1) of each node: a root with two children, redo going to the second.
+        assert!(History::decode(&encode_nodes(2, &[(0, 2), (0, 0), (0, 0)]), PATH, HASH).is_some());
+
+        assert!(History::decode(&encode_nodes(3, &[(0, 2), (0, 0), (0, 0)]), PATH, HASH).is_none(), "current");
+        assert!(History::decode(&encode_nodes(0, &[(0, 2), (2, 0), (0, 0)]), PATH, HASH).is_none(), "parent after child");
+        assert!(History::decode(&encode_nodes(0, &[(0, 2), (1, 0), (9, 0)]), PATH, HASH).is_none(), "parent");
+        assert!(History::decode(&encode_nodes(0, &[(0, 4), (0, 0), (0, 0)]), PATH, HASH).is_none(), "redo");
+        assert!(History::decode(&encode_nodes(0, &[(0, 2), (0, 3), (0, 0)]), PATH, HASH).is_none(), "redo to a sibling");
+        assert!(History::decode(&encode_nodes(0, &[(0, 1), (0, 0)]), PATH, HASH).is_none(), "redo to itself");
+        assert!(History::decode(&encode_nodes(0, &[]), PATH, HASH).is_none(), "no root");
+        assert!(History::decode(&encode_nodes(0, &[(0, u64::MAX)]), PATH, HASH).is_none(), "redo past usize");
+    }
+
+    /// Commits an edit inserting `text` at `at` by a command of `kind`, on one row.
+    fn type_at(history: &mut History, kind: EditKind, at: usize, text: &[u8]) {
+        history.record(edit(at, b"", text));
+        history.commit(kind, Cursor::new(at, 0), Cursor::new(at + text.len(), 0));
+    }
+
+    #[test]
+    fn typing_runs_together() {
+        let mut history = History::default();
+        type_at(&mut history, EditKind::Insert, 0, b"a");
+        type_at(&mut history, EditKind::Insert, 1, b"b");
+        assert_eq!((history.state(), history.step(1).edits.len()), (1, 2));
+        // Not after the cursor moved, after another kind of command, or after saving.
+        type_at(&mut history, EditKind::Insert, 0, b"c");
+        type_at(&mut history, EditKind::Delete, 1, b"");
+        type_at(&mut history, EditKind::Other, 1, b"d");
+        type_at(&mut history, EditKind::Other, 2, b"e");
+        history.seal();
+        type_at(&mut history, EditKind::Insert, 3, b"f");
+        assert_eq!(history.last_state(), 6);
+        assert_eq!(history.undo_target(), Some(5));
+    }
+
+    #[test]
+    fn switching_branches() {
+        let mut history = branching();
+        // Redo follows the branch last made, from state 1 to 3.
+        history.go_to(1);
+        assert_eq!((history.branch(), history.redo_target()), (Some((2, 2)), Some(3)));
+        assert_eq!(history.next_branch(), Some((1, 2)));
+        assert_eq!(history.redo_target(), Some(2));
+        assert_eq!(history.next_branch(), Some((2, 2)));
+        history.go_to(0);
+        assert_eq!((history.branch(), history.next_branch()), (None, None));
+
+        // Going from one branch to the other reverts up to where they meet.
+        history.go_to(2);
+        assert_eq!(history.go_to(3), [Move::Revert(2), Move::Apply(3)]);
+        history.go_to(1);
+        assert_eq!(history.redo_target(), Some(3));
+    }
+
+    #[test]
+    fn travelling_in_time() {
+        let mut history = branching();
+        for (node, time) in history.nodes.iter_mut().zip([100, 160, 220, 400]) {
+            node.time = time;
+        }
+        assert_eq!(history.travel_target(Travel::Seconds(59)), 0);
+        assert_eq!(history.travel_target(Travel::Seconds(60)), 1);
+        assert_eq!(history.travel_target(Travel::Seconds(1000)), 3);
+        history.go_to(3);
+        assert_eq!(history.travel_target(Travel::Seconds(-180)), 2);
+        assert_eq!(history.travel_target(Travel::Seconds(-181)), 1);
+        assert_eq!(history.travel_target(Travel::Seconds(-1000)), 0);
+        assert_eq!(history.travel_target(Travel::Steps(-2)), 1);
+        assert_eq!(history.travel_target(Travel::Steps(5)), 3);
+
+        // State 2 was made after the clock was set back: it counts as made when state 1 was.
+        history.nodes[2].time = 50;
+        history.go_to(0);
+        assert_eq!(history.travel_target(Travel::Seconds(60)), 2);
+        assert_eq!(history.travel_target(Travel::Seconds(59)), 0);
+    }
+}
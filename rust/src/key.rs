@@ -0,0 +1,536 @@
+//! Key decoding, the equivalent of `editorReadKey` in the C editor.
+//!
+//! Instead of mapping escape sequences to magic integers (`ARROW_LEFT = 1000`, ...),
+//! bytes decode into an [`Event`]: mostly a [`Key`], which is a [`KeyCode`] plus the
+//! [`Modifiers`] held with it. [`decode`] is a pure function over a byte slice, so anything
+//! that produces bytes (the terminal, a file, a test) can be turned into events.
+
+use std::io::{self, Read};
+use std::ops::BitOr;
+
+/// Bracketed paste markers. With `ESC [?2004h` enabled, the terminal wraps pasted text in these
+/// so it can be told apart from typing.
+pub const PASTE_START: &[u8] = b"\x1b[200~";
+pub const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// How long to wait after an `ESC` byte for the rest of an escape sequence
+/// before deciding the user pressed the Escape key on its own.
+pub const ESCAPE_TIMEOUT_MS: u64 = 50;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers(0);
+    pub const SHIFT: Modifiers = Modifiers(1 << 0);
+    pub const ALT: Modifiers = Modifiers(1 << 1);
+    pub const CTRL: Modifiers = Modifiers(1 << 2);
+
+    pub const fn union(self, other: Modifiers) -> Modifiers {
+        Modifiers(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Decodes the modifier parameter of a CSI sequence, as in `ESC [1;5C` for Ctrl+Right.
+    /// The parameter is 1 plus a bitmask of shift (1), alt (2) and ctrl (4).
+    fn from_param(param: u32) -> Modifiers {
+        Modifiers((param.saturating_sub(1) & 0b111) as u8)
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, other: Modifiers) -> Modifiers {
+        self.union(other)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    /// A printable character. Shift is already folded into the character (`'A'`, not Shift+`'a'`),
+    /// and Ctrl+letter is the lowercase letter with [`Modifiers::CTRL`].
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Esc,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    F(u8), // function keys F1–F12
+    /// An escape sequence or byte we don't understand. It is consumed and ignored.
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: Modifiers,
+}
+
+impl Key {
+    pub const fn new(code: KeyCode) -> Key {
+        Key::with(code, Modifiers::NONE)
+    }
+
+    pub const fn with(code: KeyCode, modifiers: Modifiers) -> Key {
+        Key { code, modifiers }
+    }
+
+    pub const fn char(c: char) -> Key {
+        Key::new(KeyCode::Char(c))
+    }
+
+    /// Ctrl plus a letter, the equivalent of the C `CTRL_KEY` macro.
+    pub const fn ctrl(c: char) -> Key {
+        Key::with(KeyCode::Char(c), Modifiers::CTRL)
+    }
+
+    pub const fn alt(c: char) -> Key {
+        Key::with(KeyCode::Char(c), Modifiers::ALT)
+    }
+}
+
+impl From<KeyCode> for Key {
+    fn from(code: KeyCode) -> Key {
+        Key::new(code)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseKind {
+    Press(MouseButton),
+    Release(MouseButton),
+    Drag(MouseButton), // moved with the button held down
+    ScrollUp,
+    ScrollDown,
+}
+
+/// A mouse report. `column` and `row` are 0-based screen cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub kind: MouseKind,
+    pub column: usize,
+    pub row: usize,
+    pub modifiers: Modifiers,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Key(Key),
+    /// Text pasted in one go with bracketed paste, newlines normalized to `\n`.
+    Paste(String),
+    Mouse(MouseEvent),
+}
+
+impl From<Key> for Event {
+    fn from(key: Key) -> Event {
+        Event::Key(key)
+    }
+}
+
+/*** decoding ***/
+
+/// Decodes the first event in `input` and returns it with the number of bytes it used.
+///
+/// Returns `None` when `input` is empty or is only the beginning of a sequence.
+/// When `timed_out` is set no more bytes are coming, so whatever is there is taken as it is:
+/// a lone `ESC` is the Escape key, and `ESC` followed by a key is that key with Alt.
+/// A paste is the exception, it is only complete once its end marker has arrived.
+pub fn decode(input: &[u8], timed_out: bool) -> Option<(Event, usize)> {
+    if input.starts_with(PASTE_START) {
+        return decode_paste(&input[PASTE_START.len()..]).map(|(text, len)| (Event::Paste(text), len + PASTE_START.len()));
+    }
+    if input.starts_with(b"\x1b[<") {
+        return decode_mouse(&input[3..]).map(|(event, len)| (event, len + 3));
+    }
+    decode_key(input, timed_out).map(|(key, len)| (Event::Key(key), len))
+}
+
+fn decode_key(input: &[u8], timed_out: bool) -> Option<(Key, usize)> {
+    let &first = input.first()?;
+
+    if first != b'\x1b' {
+        return decode_plain(input, timed_out);
+    }
+
+    let rest = &input[1..];
+    let decoded = match rest.first() {
+        None => None,
+        Some(b'[') => decode_csi(&rest[1..]).map(|(key, len)| (key, len + 2)),
+        Some(b'O') => decode_ss3(&rest[1..]).map(|(key, len)| (key, len + 2)),
+        Some(b'\x1b') => Some((Key::new(KeyCode::Esc), 1)), // the first ESC was pressed on its own
+        Some(_) => decode_plain(rest, timed_out).map(|(key, len)| (alt(key), len + 1)),
+    };
+
+    match decoded {
+        Some(decoded) => Some(decoded),
+        None if !timed_out => None, // wait for the rest of the sequence
+        None if rest.is_empty() => Some((Key::new(KeyCode::Esc), 1)),
+        // An unfinished `ESC [` or `ESC O` that never completed was Alt+'[' or Alt+'O'.
+        None => Some((Key::alt(rest[0] as char), 2)),
+    }
+}
+
+/// Decodes the pasted text up to the end marker. `input` starts right after the start marker.
+fn decode_paste(input: &[u8]) -> Option<(String, usize)> {
+    let end = input.windows(PASTE_END.len()).position(|window| window == PASTE_END)?;
+    let text = String::from_utf8_lossy(&input[..end]);
+    // Terminals send the Enter key's '\r' for line breaks in pasted text.
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    Some((text, end + PASTE_END.len()))
+}
+
+/// Decodes an SGR mouse report, `ESC [< button ; column ; row` followed by `M` for a press
+/// or `m` for a release. `input` starts right after the `<`.
+///
+/// The button code keeps the button in its low two bits, adds 4 for Shift, 8 for Alt and 16 for Ctrl,
+/// 32 for motion with a button held, and 64 for the wheel.
+fn decode_mouse(input: &[u8]) -> Option<(Event, usize)> {
+    let end = input.iter().position(|c| (0x40..=0x7e).contains(c))?;
+    let len = end + 1;
+    let params: Vec<usize> = input[..end]
+        .split(|&c| c == b';')
+        .filter_map(|param| std::str::from_utf8(param).ok().and_then(|p| p.parse().ok()))
+        .collect();
+    let (code, column, row) = match (input[end], params.as_slice()) {
+        (b'M' | b'm', &[code, column, row]) => (code, column, row),
+        _ => return Some((Event::Key(Key::new(KeyCode::Unknown)), len)),
+    };
+
+    let button = match code & 0b11 {
+        0 => MouseButton::Left,
+        1 => MouseButton::Middle,
+        _ => MouseButton::Right,
+    };
+    let kind = if code & 64 != 0 {
+        if code & 1 == 0 { MouseKind::ScrollUp } else { MouseKind::ScrollDown }
+    } else if input[end] == b'm' {
+        MouseKind::Release(button)
+    } else if code & 32 != 0 {
+        MouseKind::Drag(button)
+    } else {
+        MouseKind::Press(button)
+    };
+
+    let mut modifiers = Modifiers::NONE;
+    if code & 4 != 0 {
+        modifiers = modifiers | Modifiers::SHIFT;
+    }
+    if code & 8 != 0 {
+        modifiers = modifiers | Modifiers::ALT;
+    }
+    if code & 16 != 0 {
+        modifiers = modifiers | Modifiers::CTRL;
+    }
+
+    let event = MouseEvent {
+        kind,
+        column: column.saturating_sub(1), // the terminal counts from 1
+        row: row.saturating_sub(1),
+        modifiers,
+    };
+    Some((Event::Mouse(event), len))
+}
+
+/// Decodes a key that doesn't start with `ESC`: a control byte or a UTF-8 character.
+fn decode_plain(input: &[u8], timed_out: bool) -> Option<(Key, usize)> {
+    let first = input[0];
+    let key = match first {
+        b'\r' | b'\n' => Key::new(KeyCode::Enter),
+        b'\t' => Key::new(KeyCode::Tab),
+        127 => Key::new(KeyCode::Backspace), // Backspace is byte 127
+        0 => Key::ctrl(' '),
+        // Ctrl-A is 1 through Ctrl-Z at 26: the Ctrl key strips bits 5 and 6 off the letter.
+        1..=26 => Key::ctrl((b'a' + first - 1) as char),
+        b'\x1b' => Key::new(KeyCode::Esc),
+        28..=31 => Key::ctrl((first + 64) as char), // Ctrl-\ Ctrl-] Ctrl-^ Ctrl-_
+        0x20..=0x7e => Key::char(first as char),
+        _ => return decode_utf8(input, timed_out),
+    };
+    Some((key, 1))
+}
+
+fn decode_utf8(input: &[u8], timed_out: bool) -> Option<(Key, usize)> {
+    let len = match input[0] {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Some((Key::new(KeyCode::Unknown), 1)), // a continuation byte on its own
+    };
+    if input.len() < len {
+        return if timed_out { Some((Key::new(KeyCode::Unknown), input.len())) } else { None };
+    }
+    match std::str::from_utf8(&input[..len]) {
+        Ok(s) => s.chars().next().map(|c| (Key::char(c), len)),
+        Err(_) => Some((Key::new(KeyCode::Unknown), 1)),
+    }
+}
+
+fn alt(key: Key) -> Key {
+    Key::with(key.code, key.modifiers | Modifiers::ALT)
+}
+
+/// Decodes what follows `ESC [`. `input` starts right after the `[`.
+///
+/// The sequence is a list of numeric parameters separated by `;`, ended by a final byte:
+/// `ESC [A` is Up, `ESC [5~` is Page Up, and `ESC [1;5C` is Ctrl+Right.
+fn decode_csi(input: &[u8]) -> Option<(Key, usize)> {
+    let end = input.iter().position(|c| (0x40..=0x7e).contains(c))?;
+    let len = end + 1;
+    let params: Vec<u32> = input[..end]
+        .split(|&c| c == b';')
+        .map(|param| std::str::from_utf8(param).ok().and_then(|p| p.parse().ok()).unwrap_or(0))
+        .collect();
+    let param = |i: usize| params.get(i).copied().unwrap_or(0);
+    let modifiers = Modifiers::from_param(param(1));
+
+    let code = match input[end] {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'P' => KeyCode::F(1),
+        b'Q' => KeyCode::F(2),
+        b'R' => KeyCode::F(3),
+        b'S' => KeyCode::F(4),
+        b'Z' => return Some((Key::with(KeyCode::Tab, Modifiers::SHIFT), len)), // Shift+Tab
+        b'~' => match param(0) {
+            1 | 7 => KeyCode::Home,
+            2 => KeyCode::Insert,
+            3 => KeyCode::Delete,
+            4 | 8 => KeyCode::End,
+            5 => KeyCode::PageUp,
+            6 => KeyCode::PageDown,
+            n @ 11..=15 => KeyCode::F((n - 10) as u8), // F1–F5
+            n @ 17..=21 => KeyCode::F((n - 11) as u8), // F6–F10
+            n @ 23..=24 => KeyCode::F((n - 12) as u8), // F11–F12
+            _ => KeyCode::Unknown,
+        },
+        _ => KeyCode::Unknown,
+    };
+    Some((Key::with(code, modifiers), len))
+}
+
+/// Decodes what follows `ESC O`, which some terminals send for Home, End, arrows and F1–F4.
+fn decode_ss3(input: &[u8]) -> Option<(Key, usize)> {
+    let code = match input.first()? {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'P' => KeyCode::F(1),
+        b'Q' => KeyCode::F(2),
+        b'R' => KeyCode::F(3),
+        b'S' => KeyCode::F(4),
+        _ => KeyCode::Unknown,
+    };
+    Some((Key::new(code), 1))
+}
+
+/// Buffers bytes as they arrive and hands out events once they are complete.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    pending: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Takes the next complete event, see [`decode`] for `timed_out`.
+    pub fn next_event(&mut self, timed_out: bool) -> Option<Event> {
+        let (event, len) = decode(&self.pending, timed_out)?;
+        self.pending.drain(..len);
+        Some(event)
+    }
+}
+
+/// Decodes the bytes read from `input` into events.
+pub fn events<R: Read>(input: R) -> Events<R> {
+    Events {
+        input,
+        decoder: Decoder::new(),
+        eof: false,
+    }
+}
+
+/// An iterator over the events in a byte stream. A blocking reader can't time out,
+/// so a trailing `ESC` is only taken as the Escape key at the end of the stream.
+pub struct Events<R> {
+    input: R,
+    decoder: Decoder,
+    eof: bool,
+}
+
+impl<R: Read> Iterator for Events<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        loop {
+            if let Some(event) = self.decoder.next_event(self.eof) {
+                return Some(Ok(event));
+            }
+            if self.eof {
+                return None;
+            }
+
+            let mut buf = [0; 64];
+            match self.input.read(&mut buf) {
+                Ok(0) => self.eof = true,
+                Ok(n) => self.decoder.push(&buf[..n]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(input: &[u8]) -> Option<(Key, usize)> {
+        match decode(input, false)? {
+            (Event::Key(key), len) => Some((key, len)),
+            (event, _) => panic!("{:?} isn't a key", event),
+        }
+    }
+
+    fn mouse(kind: MouseKind, column: usize, row: usize) -> Event {
+        Event::Mouse(MouseEvent { kind, column, row, modifiers: Modifiers::NONE })
+    }
+
+    #[test]
+    fn arrows_with_modifiers() {
+        assert_eq!(key(b"\x1b[A"), Some((Key::new(KeyCode::Up), 3)));
+        assert_eq!(key(b"\x1b[1;5C"), Some((Key::with(KeyCode::Right, Modifiers::CTRL), 6)));
+        assert_eq!(key(b"\x1b[1;2D"), Some((Key::with(KeyCode::Left, Modifiers::SHIFT), 6)));
+        let ctrl_shift = Modifiers::CTRL | Modifiers::SHIFT;
+        assert_eq!(key(b"\x1b[1;6B"), Some((Key::with(KeyCode::Down, ctrl_shift), 6)));
+        assert_eq!(key(b"\x1b[1;3A"), Some((Key::with(KeyCode::Up, Modifiers::ALT), 6)));
+    }
+
+    #[test]
+    fn home_and_end_every_way_terminals_send_them() {
+        for input in [&b"\x1b[1~"[..], b"\x1b[7~", b"\x1b[H", b"\x1bOH"] {
+            assert_eq!(key(input), Some((Key::new(KeyCode::Home), input.len())), "{:?}", input);
+        }
+        for input in [&b"\x1b[4~"[..], b"\x1b[8~", b"\x1b[F", b"\x1bOF"] {
+            assert_eq!(key(input), Some((Key::new(KeyCode::End), input.len())), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn function_keys() {
+        let sequences: [&[u8]; 12] = [
+            b"\x1bOP", b"\x1bOQ", b"\x1bOR", b"\x1bOS", b"\x1b[15~", b"\x1b[17~", b"\x1b[18~", b"\x1b[19~",
+            b"\x1b[20~", b"\x1b[21~", b"\x1b[23~", b"\x1b[24~",
+        ];
+        for (n, input) in (1..).zip(sequences) {
+            assert_eq!(key(input), Some((Key::new(KeyCode::F(n)), input.len())), "F{}", n);
+        }
+        assert_eq!(key(b"\x1b[11~"), Some((Key::new(KeyCode::F(1)), 5)));
+        assert_eq!(key(b"\x1b[14~"), Some((Key::new(KeyCode::F(4)), 5)));
+        assert_eq!(key(b"\x1b[1;5P"), Some((Key::with(KeyCode::F(1), Modifiers::CTRL), 6)));
+    }
+
+    #[test]
+    fn lone_escape_waits_until_timed_out() {
+        assert_eq!(decode(b"\x1b", false), None);
+        assert_eq!(decode(b"\x1b", true), Some((Event::Key(Key::new(KeyCode::Esc)), 1)));
+        assert_eq!(decode(b"\x1b[", false), None);
+        assert_eq!(decode(b"\x1b[", true), Some((Event::Key(Key::alt('[')), 2)));
+        assert_eq!(decode(b"\x1bx", true), Some((Event::Key(Key::alt('x')), 2)));
+        assert_eq!(decode(b"\x1b\x1b[A", false), Some((Event::Key(Key::new(KeyCode::Esc)), 1)));
+    }
+
+    #[test]
+    fn utf8_split_across_reads() {
+        let mut decoder = Decoder::new();
+        let bytes = "é한".as_bytes();
+        decoder.push(&bytes[..1]);
+        assert_eq!(decoder.next_event(false), None);
+        decoder.push(&bytes[1..3]);
+        assert_eq!(decoder.next_event(false), Some(Event::Key(Key::char('é'))));
+        assert_eq!(decoder.next_event(false), None);
+        decoder.push(&bytes[3..]);
+        assert_eq!(decoder.next_event(false), Some(Event::Key(Key::char('한'))));
+        assert!(decoder.is_empty());
+
+        // A character cut short for good is dropped rather than waited on forever.
+        assert_eq!(decode(&bytes[2..4], true), Some((Event::Key(Key::new(KeyCode::Unknown)), 2)));
+    }
+
+    #[test]
+    fn bracketed_paste() {
+        let input = b"\x1b[200~one\r\ntwo\rthree\x1b[201~x";
+        let len = input.len() - 1;
+        assert_eq!(decode(input, false), Some((Event::Paste("one\ntwo\nthree".to_string()), len)));
+        // Not over until the end marker, even once no more bytes are coming.
+        assert_eq!(decode(b"\x1b[200~half", true), None);
+
+        let mut decoder = Decoder::new();
+        decoder.push(b"\x1b[200~a\x1b[A\x1b[20");
+        assert_eq!(decoder.next_event(true), None);
+        decoder.push(b"1~");
+        assert_eq!(decoder.next_event(false), Some(Event::Paste("a\x1b[A".to_string())));
+    }
+
+    #[test]
+    fn sgr_mouse() {
+        let press = mouse(MouseKind::Press(MouseButton::Left), 9, 4);
+        assert_eq!(decode(b"\x1b[<0;10;5M", false), Some((press, 10)));
+        let release = mouse(MouseKind::Release(MouseButton::Left), 9, 4);
+        assert_eq!(decode(b"\x1b[<0;10;5m", false), Some((release, 10)));
+        let drag = mouse(MouseKind::Drag(MouseButton::Left), 11, 6);
+        assert_eq!(decode(b"\x1b[<32;12;7M", false), Some((drag, 11)));
+        let right = mouse(MouseKind::Press(MouseButton::Right), 0, 0);
+        assert_eq!(decode(b"\x1b[<2;1;1M", false), Some((right, 9)));
+        assert_eq!(decode(b"\x1b[<64;1;1M", false), Some((mouse(MouseKind::ScrollUp, 0, 0), 10)));
+        assert_eq!(decode(b"\x1b[<65;1;1M", false), Some((mouse(MouseKind::ScrollDown, 0, 0), 10)));
+        let ctrl_click = Event::Mouse(MouseEvent {
+            kind: MouseKind::Press(MouseButton::Left),
+            column: 0,
+            row: 0,
+            modifiers: Modifiers::CTRL,
+        });
+        assert_eq!(decode(b"\x1b[<16;1;1M", false), Some((ctrl_click, 10)));
+        assert_eq!(decode(b"\x1b[<0;10", false), None);
+    }
+}
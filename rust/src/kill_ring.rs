@@ -0,0 +1,72 @@
+//! The text cut and copied so far, newest first, for pasting back.
+//!
+//! Like the kill ring of Emacs, it keeps more than the last entry: right after a paste,
+//! the pasted text can be swapped for the entry before it, and so on around the ring.
+
+use std::collections::VecDeque;
+
+/// How many entries the ring keeps before dropping the oldest.
+const KILL_RING_LEN: usize = 32;
+
+/// One entry of the ring.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Kill {
+    pub text: Vec<u8>,
+    pub whole_lines: bool, // cut or copied without a selection, so it is pasted above the cursor's row
+}
+
+#[derive(Debug, Default)]
+pub struct KillRing {
+    entries: VecDeque<Kill>,
+}
+
+impl KillRing {
+    pub fn push(&mut self, kill: Kill) {
+        if kill.text.is_empty() {
+            return;
+        }
+        if self.entries.len() == KILL_RING_LEN {
+            self.entries.pop_back();
+        }
+        self.entries.push_front(kill);
+    }
+
+    /// Returns entry `index`, 0 being the newest.
+    pub fn get(&self, index: usize) -> Option<&Kill> {
+        self.entries.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kill(text: &str) -> Kill {
+        Kill {
+            text: text.as_bytes().to_vec(),
+            whole_lines: false,
+        }
+    }
+
+    #[test]
+    fn only_the_newest_entries_are_kept() {
+        let mut ring = KillRing::default();
+        ring.push(kill(""));
+        assert!(ring.is_empty());
+        for i in 0..KILL_RING_LEN + 2 {
+            ring.push(kill(&i.to_string()));
+        }
+        assert_eq!(ring.len(), KILL_RING_LEN);
+        assert_eq!(ring.get(0), Some(&kill(&(KILL_RING_LEN + 1).to_string())));
+        assert_eq!(ring.get(KILL_RING_LEN - 1), Some(&kill("2")));
+        assert_eq!(ring.get(KILL_RING_LEN), None);
+    }
+}
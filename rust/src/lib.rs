@@ -0,0 +1,45 @@
+//! The core of tiny-editor: a kilo-style text editor that doesn't touch the terminal itself.
+//!
+//! [`Editor`] takes keypresses through [`Editor::process_key`] and hands back the
+//! escape sequences to draw from [`Editor::refresh_screen`], so it can be driven
+//! by a real terminal as well as by a test. [`Editor::run`] ties the two together
+//! over any [`Terminal`], including the in-memory [`VirtualTerminal`].
+
+pub mod buffer;
+pub mod clipboard;
+pub mod cursor;
+pub mod data;
+pub mod editor;
+pub mod guard;
+pub mod history;
+pub mod key;
+pub mod kill_ring;
+pub mod piece_table;
+pub mod prompt_history;
+pub mod render;
+pub mod row;
+pub mod search;
+pub mod syntax;
+pub mod terminal;
+pub mod window;
+
+pub use buffer::Buffer;
+pub use clipboard::Clipboard;
+pub use cursor::{Cursor, Direction};
+pub use editor::Editor;
+pub use guard::TerminalGuard;
+pub use history::{EditKind, History};
+pub use key::{Event, Key, KeyCode, Modifiers, MouseButton, MouseEvent, MouseKind};
+pub use kill_ring::{Kill, KillRing};
+pub use piece_table::PieceTable;
+pub use prompt_history::{PromptHistory, PromptKind};
+pub use row::Row;
+pub use search::{Matcher, SearchOptions};
+pub use terminal::{TermionTerminal, Terminal, VirtualTerminal};
+pub use window::{Layout, Rect, Split, Window};
+
+/*** defines ***/
+
+pub const TINY_VERSION: &str = "0.0.1";
+pub const TAB_STOP: usize = 8;
+pub const QUIT_TIMES: u32 = 2;
@@ -1,8 +1,52 @@
-use termion::raw::IntoRawMode;
-use std::io::{self, Write};
+use std::env;
+use std::io;
+use std::process;
 
-fn main() {
-    let stdout = io::stdout().into_raw_mode().unwrap();
+use tiny_editor::{Clipboard, Editor, TermionTerminal, Terminal, TerminalGuard};
+
+fn run() -> io::Result<()> {
+    let _guard = TerminalGuard::new()?;
+    let mut terminal = TermionTerminal::new();
+    terminal.enable_raw_mode()?;
+    let (rows, cols) = terminal.size()?;
+
+    let mut editor = Editor::new(rows, cols);
+    let mut filenames = Vec::new();
+    let mut clipboard = Clipboard::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--persist-undo" => editor.set_persistent_undo(true),
+            "--copy-command" => clipboard.copy_command = args.next(),
+            "--paste-command" => clipboard.paste_command = args.next(),
+            "--no-osc52" => clipboard.osc52 = false,
+            "--tab-line" => editor.set_tab_line(true),
+            _ => filenames.push(arg),
+        }
+    }
+    editor.set_clipboard(clipboard);
+    if let Err(err) = editor.load_prompt_history() {
+        editor.set_status_message(&format!("Can't read prompt history: {}", err));
+    }
+    for filename in &filenames {
+        editor.open(filename)?;
+    }
+    editor.switch_buffer(0);
+
+    if editor.status_message().is_empty() {
+        editor.set_status_message("HELP: Ctrl-S = save | Ctrl-Q = quit | Ctrl-F = find | Ctrl-Z = undo | Ctrl-Y = redo | Ctrl-B = buffers");
+    }
 
-    write!(stdout, "{}", termion::clear::All).unwrap();
+    let result = editor.run(&mut terminal);
+    terminal.disable_raw_mode()?;
+    result
+}
+
+fn main() {
+    // `run` returns before reporting the error so the guard is dropped
+    // (and cooked mode restored) first, the way `atexit(disableRawMode)` does in C.
+    if let Err(err) = run() {
+        eprintln!("tiny-editor: {}", err);
+        process::exit(1);
+    }
 }
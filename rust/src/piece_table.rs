@@ -0,0 +1,809 @@
+//! The text of a buffer, kept as a piece table instead of a `Vec` of rows.
+//!
+//! The file is read once into the *original* store and never changed. Everything typed
+//! afterwards is appended to the *add* store, and the text is the sequence of pieces
+//! (spans of one store or the other) that says which bytes come in what order.
+//! Inserting splits a piece and adds one for the new bytes; deleting drops or trims pieces.
+//!
+//! The pieces live in a treap, a binary search tree balanced by random priorities,
+//! ordered by position in the text. Every node knows the bytes, chars and newlines of its
+//! subtree, and every store keeps an index of its newlines and chars, so inserts, deletes
+//! and conversions between byte, char and line offsets are O(log n) however big the file is.
+
+use std::ops::{Deref, Range};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use memmap2::Mmap;
+
+/// Size of the blocks the char index is kept in. Counting the chars of a span scans at most this many bytes.
+const CHAR_BLOCK: usize = 4096;
+/// How much of a mapped file is indexed at a time. A multiple of `CHAR_BLOCK`.
+const INDEX_CHUNK: usize = 4 << 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Source {
+    Original,
+    Add,
+}
+
+/// The bytes of a store: in memory, or a file mapped into memory.
+#[derive(Debug)]
+enum Bytes {
+    Owned(Vec<u8>),
+    Mapped(Arc<Mmap>),
+}
+
+impl Default for Bytes {
+    fn default() -> Self {
+        Bytes::Owned(Vec::new())
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Bytes::Owned(bytes) => bytes,
+            Bytes::Mapped(map) => map,
+        }
+    }
+}
+
+/// Where the newlines and chars are in the first `len` bytes of a store.
+#[derive(Debug, Default)]
+struct Index {
+    len: usize, // number of bytes indexed
+    newlines: Vec<usize>, // offsets of every '\n', in order
+    blocks: Vec<usize>, // number of chars before the start of each block of CHAR_BLOCK bytes
+    chars: usize, // number of chars in the bytes indexed
+}
+
+impl Index {
+    /// Indexes `bytes`, which come right after the bytes indexed so far.
+    fn scan(&mut self, bytes: &[u8]) {
+        for (i, &c) in bytes.iter().enumerate() {
+            let at = self.len + i;
+            if at.is_multiple_of(CHAR_BLOCK) {
+                self.blocks.push(self.chars);
+            }
+            if c == b'\n' {
+                self.newlines.push(at);
+            }
+            if is_char_boundary(c) {
+                self.chars += 1;
+            }
+        }
+        self.len += bytes.len();
+    }
+
+    /// Takes in `next`, the index of the bytes that come right after these.
+    fn append(&mut self, next: Index) {
+        self.newlines.extend(next.newlines);
+        self.blocks.extend(next.blocks);
+        self.len = next.len;
+        self.chars = next.chars;
+    }
+}
+
+/// One of the two stores text comes from, with the index needed to count what is in a span of it.
+#[derive(Debug, Default)]
+struct Store {
+    bytes: Bytes,
+    index: Index, // covers all of `bytes`, except for a mapped file that is still loading
+}
+
+impl Store {
+    fn extend(&mut self, bytes: &[u8]) {
+        match &mut self.bytes {
+            Bytes::Owned(owned) => owned.extend_from_slice(bytes),
+            Bytes::Mapped(_) => unreachable!("mapped files are never written to"),
+        }
+        self.index.scan(bytes);
+    }
+
+    /// Number of newlines before byte `at`.
+    fn newlines_before(&self, at: usize) -> usize {
+        self.index.newlines.partition_point(|&n| n < at)
+    }
+
+    /// Number of chars that start before byte `at`.
+    fn chars_before(&self, at: usize) -> usize {
+        let block = at / CHAR_BLOCK;
+        match self.index.blocks.get(block) {
+            Some(&chars) => chars + count_chars(&self.bytes[block * CHAR_BLOCK..at]),
+            None => self.index.chars, // `at` is the end of what is indexed
+        }
+    }
+
+    /// Byte offset of char number `n` of the store, or the end of the index if there are not that many.
+    fn char_to_byte(&self, n: usize) -> usize {
+        let block = self.index.blocks.partition_point(|&chars| chars <= n).saturating_sub(1);
+        let mut chars = self.index.blocks.get(block).copied().unwrap_or(0);
+        let start = block * CHAR_BLOCK;
+        for (i, &c) in self.bytes[start.min(self.index.len)..self.index.len].iter().enumerate() {
+            if is_char_boundary(c) {
+                if chars == n {
+                    return start + i;
+                }
+                chars += 1;
+            }
+        }
+        self.index.len
+    }
+}
+
+/// A span of one of the stores.
+#[derive(Clone, Copy, Debug)]
+struct Piece {
+    source: Source,
+    start: usize,
+    len: usize,
+}
+
+/// What a piece or a whole subtree holds.
+#[derive(Clone, Copy, Debug, Default)]
+struct Summary {
+    len: usize, // bytes
+    chars: usize,
+    newlines: usize,
+}
+
+impl Summary {
+    fn add(self, other: Summary) -> Summary {
+        Summary {
+            len: self.len + other.len,
+            chars: self.chars + other.chars,
+            newlines: self.newlines + other.newlines,
+        }
+    }
+}
+
+type Tree = Option<Box<Node>>;
+
+#[derive(Debug)]
+struct Node {
+    piece: Piece,
+    own: Summary, // of the piece alone
+    total: Summary, // of the whole subtree
+    priority: u32, // every node's priority is at least that of its children
+    left: Tree,
+    right: Tree,
+}
+
+impl Node {
+    fn update(&mut self) {
+        self.total = summary(&self.left).add(self.own).add(summary(&self.right));
+    }
+}
+
+fn summary(tree: &Tree) -> Summary {
+    tree.as_ref().map_or(Summary::default(), |node| node.total)
+}
+
+#[derive(Debug)]
+pub struct PieceTable {
+    original: Store,
+    add: Store,
+    root: Tree,
+    seed: u32, // state of the generator of node priorities
+    loading: Option<Receiver<Index>>, // the rest of a mapped file, as it gets indexed
+}
+
+impl Default for PieceTable {
+    fn default() -> Self {
+        PieceTable::new(Vec::new())
+    }
+}
+
+impl PieceTable {
+    /// Makes a table whose text is `original`.
+    pub fn new(original: Vec<u8>) -> PieceTable {
+        let mut table = PieceTable::empty(Bytes::Owned(Vec::new()));
+        table.original.extend(&original);
+        table.append(Piece {
+            source: Source::Original,
+            start: 0,
+            len: original.len(),
+        });
+        table
+    }
+
+    /// Makes a table over a file mapped into memory, without reading all of it first.
+    ///
+    /// Only the first few megabytes are indexed right away. The rest is indexed on a thread
+    /// of its own and joins the text as [`PieceTable::poll_loading`] picks it up,
+    /// so until then the text is a prefix of the file.
+    pub fn mapped(map: Mmap) -> PieceTable {
+        let map = Arc::new(map);
+        let mut table = PieceTable::empty(Bytes::Mapped(Arc::clone(&map)));
+
+        let first = INDEX_CHUNK.min(map.len());
+        table.original.index.scan(&map[..first]);
+        table.append(Piece {
+            source: Source::Original,
+            start: 0,
+            len: first,
+        });
+
+        if first < map.len() {
+            let (sender, receiver) = mpsc::channel();
+            let (mut len, mut chars) = (first, table.original.index.chars);
+            thread::spawn(move || {
+                while len < map.len() {
+                    let mut next = Index {
+                        len,
+                        chars,
+                        ..Index::default()
+                    };
+                    next.scan(&map[len..(len + INDEX_CHUNK).min(map.len())]);
+                    (len, chars) = (next.len, next.chars);
+                    if sender.send(next).is_err() {
+                        return; // the table is gone
+                    }
+                }
+            });
+            table.loading = Some(receiver);
+        }
+        table
+    }
+
+    fn empty(original: Bytes) -> PieceTable {
+        PieceTable {
+            original: Store {
+                bytes: original,
+                index: Index::default(),
+            },
+            add: Store::default(),
+            root: None,
+            seed: 0x9e37_79b9,
+            loading: None,
+        }
+    }
+
+    /// Length of the text in bytes.
+    pub fn len(&self) -> usize {
+        summary(&self.root).len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of chars in the text, counted by the bytes that start one: a stray UTF-8 continuation
+    /// byte counts as none, and any other byte that isn't valid UTF-8 as one char.
+    pub fn char_count(&self) -> usize {
+        summary(&self.root).chars
+    }
+
+    /// Number of lines, which is one more than the number of newlines: a text that ends with
+    /// a newline has an empty last line.
+    pub fn line_count(&self) -> usize {
+        summary(&self.root).newlines + 1
+    }
+
+    /// Whether the original text is a file mapped into memory, which must not be written to while we use it.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.original.bytes, Bytes::Mapped(_))
+    }
+
+    /*** loading ***/
+
+    pub fn is_loading(&self) -> bool {
+        self.loading.is_some()
+    }
+
+    /// Returns how many bytes of a mapped file are in the text so far and how big the file is,
+    /// or `None` once all of it is.
+    pub fn loading_progress(&self) -> Option<(usize, usize)> {
+        self.loading.as_ref()?;
+        Some((self.original.index.len, self.original.bytes.len()))
+    }
+
+    /// Adds the parts of a mapped file indexed since the last call to the end of the text.
+    /// Returns whether the text grew.
+    ///
+    /// Only call it on a text that hasn't been edited, since the new part is simply appended.
+    pub fn poll_loading(&mut self) -> bool {
+        let receiver = match self.loading.take() {
+            Some(receiver) => receiver,
+            None => return false,
+        };
+        let mut grew = false;
+        loop {
+            match receiver.try_recv() {
+                Ok(index) => {
+                    self.take_index(index);
+                    grew = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return grew,
+            }
+        }
+        if self.original.index.len < self.original.bytes.len() {
+            self.loading = Some(receiver);
+        }
+        grew
+    }
+
+    /// Waits for the rest of a mapped file to be indexed and adds it to the text.
+    pub fn finish_loading(&mut self) {
+        if let Some(receiver) = self.loading.take() {
+            for index in receiver {
+                self.take_index(index);
+            }
+        }
+    }
+
+    fn take_index(&mut self, index: Index) {
+        let start = self.original.index.len;
+        self.original.index.append(index);
+        self.append(Piece {
+            source: Source::Original,
+            start,
+            len: self.original.index.len - start,
+        });
+    }
+
+    /*** editing ***/
+
+    /// Inserts `bytes` at byte `at`, or at the end if `at` is past it.
+    pub fn insert(&mut self, at: usize, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        let at = at.min(self.len());
+        let start = self.add.bytes.len();
+        self.add.extend(bytes);
+
+        let piece = Piece {
+            source: Source::Add,
+            start,
+            len: bytes.len(),
+        };
+        let root = self.root.take();
+        let (left, right) = self.split(root, at);
+        let left = self.push(left, piece);
+        self.root = merge(left, right);
+    }
+
+    /// Removes the bytes in `range`.
+    pub fn delete(&mut self, range: Range<usize>) {
+        let end = range.end.min(self.len());
+        if range.start >= end {
+            return;
+        }
+        let root = self.root.take();
+        let (left, rest) = self.split(root, range.start);
+        let (_, right) = self.split(rest, end - range.start);
+        self.root = merge(left, right);
+    }
+
+    /*** reading ***/
+
+    /// Returns the byte at `at`.
+    pub fn byte(&self, at: usize) -> Option<u8> {
+        let (piece, offset, _) = self.locate(at)?;
+        Some(self.store(piece.source).bytes[piece.start + offset])
+    }
+
+    /// Copies out the bytes in `range`.
+    pub fn slice(&self, range: Range<usize>) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(range.len());
+        let end = range.end.min(self.len());
+        if range.start < end {
+            self.collect(&self.root, range.start, end, &mut buf);
+        }
+        buf
+    }
+
+    /// Copies out line `n` without its newline.
+    pub fn line(&self, n: usize) -> Vec<u8> {
+        let start = self.line_to_byte(n);
+        let end = self.line_to_byte(n + 1);
+        let end = if end > start && self.byte(end - 1) == Some(b'\n') { end - 1 } else { end };
+        self.slice(start..end)
+    }
+
+    /// Iterates over the text one piece at a time, in order.
+    pub fn chunks(&self) -> Chunks<'_> {
+        let mut chunks = Chunks {
+            table: self,
+            stack: Vec::new(),
+        };
+        chunks.push_left(&self.root);
+        chunks
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.len());
+        for chunk in self.chunks() {
+            buf.extend_from_slice(chunk);
+        }
+        buf
+    }
+
+    /*** offset conversion ***/
+
+    /// Returns the byte offset where line `n` starts, or the length of the text if there is no such line.
+    pub fn line_to_byte(&self, n: usize) -> usize {
+        if n == 0 {
+            return 0;
+        }
+        // look for newline number `n`, counting from 1, and return the byte after it
+        let mut n = n;
+        let mut offset = 0;
+        let mut tree = &self.root;
+        while let Some(node) = tree {
+            let left = summary(&node.left);
+            if n <= left.newlines {
+                tree = &node.left;
+                continue;
+            }
+            n -= left.newlines;
+            offset += left.len;
+            if n <= node.own.newlines {
+                let store = self.store(node.piece.source);
+                let newline = store.index.newlines[store.newlines_before(node.piece.start) + n - 1];
+                return offset + (newline - node.piece.start) + 1;
+            }
+            n -= node.own.newlines;
+            offset += node.own.len;
+            tree = &node.right;
+        }
+        self.len()
+    }
+
+    /// Returns the line that byte `at` is on.
+    pub fn byte_to_line(&self, at: usize) -> usize {
+        match self.locate(at) {
+            Some((piece, offset, before)) => {
+                let store = self.store(piece.source);
+                before.newlines + store.newlines_before(piece.start + offset) - store.newlines_before(piece.start)
+            }
+            None => summary(&self.root).newlines,
+        }
+    }
+
+    /// Returns the index of the char that byte `at` belongs to.
+    pub fn byte_to_char(&self, at: usize) -> usize {
+        match self.locate(at) {
+            Some((piece, offset, before)) => {
+                let store = self.store(piece.source);
+                before.chars + store.chars_before(piece.start + offset) - store.chars_before(piece.start)
+            }
+            None => self.char_count(),
+        }
+    }
+
+    /// Returns the byte offset where char `n` starts, or the length of the text if there is no such char.
+    pub fn char_to_byte(&self, n: usize) -> usize {
+        let mut n = n;
+        let mut offset = 0;
+        let mut tree = &self.root;
+        while let Some(node) = tree {
+            let left = summary(&node.left);
+            if n < left.chars {
+                tree = &node.left;
+                continue;
+            }
+            n -= left.chars;
+            offset += left.len;
+            if n < node.own.chars {
+                let store = self.store(node.piece.source);
+                let at = store.char_to_byte(store.chars_before(node.piece.start) + n);
+                return offset + (at - node.piece.start);
+            }
+            n -= node.own.chars;
+            offset += node.own.len;
+            tree = &node.right;
+        }
+        self.len()
+    }
+
+    /*** tree ***/
+
+    fn store(&self, source: Source) -> &Store {
+        match source {
+            Source::Original => &self.original,
+            Source::Add => &self.add,
+        }
+    }
+
+    /// Returns the next priority from a xorshift generator. Treaps only need priorities
+    /// that look random, not good randomness.
+    fn next_priority(&mut self) -> u32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed
+    }
+
+    fn node(&mut self, piece: Piece) -> Box<Node> {
+        let own = self.measure(piece);
+        Box::new(Node {
+            piece,
+            own,
+            total: own,
+            priority: self.next_priority(),
+            left: None,
+            right: None,
+        })
+    }
+
+    fn measure(&self, piece: Piece) -> Summary {
+        let store = self.store(piece.source);
+        let end = piece.start + piece.len;
+        Summary {
+            len: piece.len,
+            chars: store.chars_before(end) - store.chars_before(piece.start),
+            newlines: store.newlines_before(end) - store.newlines_before(piece.start),
+        }
+    }
+
+    /// Finds the piece holding byte `at`. Returns it with the offset of `at` inside it
+    /// and a summary of everything before it.
+    fn locate(&self, at: usize) -> Option<(Piece, usize, Summary)> {
+        let mut at = at;
+        let mut before = Summary::default();
+        let mut tree = &self.root;
+        while let Some(node) = tree {
+            let left = summary(&node.left);
+            if at < left.len {
+                tree = &node.left;
+            } else if at < left.len + node.own.len {
+                return Some((node.piece, at - left.len, before.add(left)));
+            } else {
+                at -= left.len + node.own.len;
+                before = before.add(left).add(node.own);
+                tree = &node.right;
+            }
+        }
+        None
+    }
+
+    /// Splits `tree` into the first `at` bytes and the rest, cutting a piece in two if `at` falls inside it.
+    fn split(&mut self, tree: Tree, at: usize) -> (Tree, Tree) {
+        let mut node = match tree {
+            Some(node) => node,
+            None => return (None, None),
+        };
+        let left_len = summary(&node.left).len;
+        if at <= left_len {
+            let (left, right) = self.split(node.left.take(), at);
+            node.left = right;
+            node.update();
+            (left, Some(node))
+        } else if at >= left_len + node.own.len {
+            let (left, right) = self.split(node.right.take(), at - left_len - node.own.len);
+            node.right = left;
+            node.update();
+            (Some(node), right)
+        } else {
+            let cut = at - left_len;
+            let tail = Piece {
+                source: node.piece.source,
+                start: node.piece.start + cut,
+                len: node.piece.len - cut,
+            };
+            let tail = self.node(tail);
+            node.piece.len = cut;
+            node.own = self.measure(node.piece);
+            let right = merge(Some(tail), node.right.take());
+            node.update();
+            (Some(node), right)
+        }
+    }
+
+    fn append(&mut self, piece: Piece) {
+        let root = self.root.take();
+        self.root = self.push(root, piece);
+    }
+
+    /// Adds `piece` to the end of `tree`.
+    ///
+    /// Typing one char after another would make a piece per char, so a piece that follows
+    /// the last one in its store as well as in the text just makes the last one longer.
+    fn push(&mut self, mut tree: Tree, piece: Piece) -> Tree {
+        if piece.len == 0 || self.grow_last(&mut tree, piece) {
+            return tree;
+        }
+        let node = self.node(piece);
+        merge(tree, Some(node))
+    }
+
+    /// Makes the last piece of `tree` cover `piece` as well if `piece` comes right after it in the same store.
+    fn grow_last(&self, tree: &mut Tree, piece: Piece) -> bool {
+        let node = match tree {
+            Some(node) => node,
+            None => return false,
+        };
+        let grown = if node.right.is_some() {
+            self.grow_last(&mut node.right, piece)
+        } else if node.piece.source == piece.source && node.piece.start + node.piece.len == piece.start {
+            node.piece.len += piece.len;
+            node.own = self.measure(node.piece);
+            true
+        } else {
+            false
+        };
+        if grown {
+            node.update();
+        }
+        grown
+    }
+
+    /// Appends the bytes of `tree` from `start` to `end`, both relative to the start of `tree`, to `buf`.
+    fn collect(&self, tree: &Tree, start: usize, end: usize, buf: &mut Vec<u8>) {
+        let node = match tree {
+            Some(node) => node,
+            None => return,
+        };
+        let left_len = summary(&node.left).len;
+        if start < left_len {
+            self.collect(&node.left, start, end.min(left_len), buf);
+        }
+        let piece_end = left_len + node.own.len;
+        if start < piece_end && end > left_len {
+            let from = start.max(left_len) - left_len;
+            let to = end.min(piece_end) - left_len;
+            let bytes = &self.store(node.piece.source).bytes;
+            buf.extend_from_slice(&bytes[node.piece.start + from..node.piece.start + to]);
+        }
+        if end > piece_end {
+            self.collect(&node.right, start.max(piece_end) - piece_end, end - piece_end, buf);
+        }
+    }
+}
+
+/// Joins two trees, with all of `left` coming before all of `right` in the text.
+fn merge(left: Tree, right: Tree) -> Tree {
+    match (left, right) {
+        (None, tree) | (tree, None) => tree,
+        (Some(mut left), Some(mut right)) => {
+            if left.priority >= right.priority {
+                left.right = merge(left.right.take(), Some(right));
+                left.update();
+                Some(left)
+            } else {
+                right.left = merge(Some(left), right.left.take());
+                right.update();
+                Some(right)
+            }
+        }
+    }
+}
+
+/// Iterator over the pieces of a [`PieceTable`], returned by [`PieceTable::chunks`].
+pub struct Chunks<'a> {
+    table: &'a PieceTable,
+    stack: Vec<&'a Node>, // nodes whose piece and right subtree are still to come
+}
+
+impl<'a> Chunks<'a> {
+    fn push_left(&mut self, mut tree: &'a Tree) {
+        while let Some(node) = tree {
+            self.stack.push(node);
+            tree = &node.left;
+        }
+    }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let node = self.stack.pop()?;
+        self.push_left(&node.right);
+        let piece = node.piece;
+        Some(&self.table.store(piece.source).bytes[piece.start..piece.start + piece.len])
+    }
+}
+
+/// Whether `c` starts a char, that is, is not a UTF-8 continuation byte.
+fn is_char_boundary(c: u8) -> bool {
+    c & 0xc0 != 0x80
+}
+
+fn count_chars(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&c| is_char_boundary(c)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A xorshift generator, so every run makes the same edits.
+    struct Rng(u32);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0 as usize % n.max(1)
+        }
+    }
+
+    /// Checks everything the table says about its text against `model`.
+    fn check(table: &PieceTable, model: &[u8]) {
+        assert_eq!(table.to_bytes(), model);
+        assert_eq!(table.len(), model.len());
+        let newlines: Vec<usize> = (0..model.len()).filter(|&i| model[i] == b'\n').collect();
+        let chars: Vec<usize> = (0..model.len()).filter(|&i| is_char_boundary(model[i])).collect();
+        assert_eq!(table.line_count(), newlines.len() + 1);
+        assert_eq!(table.char_count(), chars.len());
+
+        // Every offset of a short text, and a few hundred spread over a long one.
+        let step = model.len() / 300 + 1;
+        for n in (0..newlines.len() + 2).step_by(step) {
+            let expected = match n {
+                0 => 0,
+                n => newlines.get(n - 1).map_or(model.len(), |&at| at + 1),
+            };
+            assert_eq!(table.line_to_byte(n), expected, "line_to_byte({})", n);
+        }
+        for n in (0..chars.len() + 2).step_by(step) {
+            assert_eq!(table.char_to_byte(n), chars.get(n).copied().unwrap_or(model.len()), "char_to_byte({})", n);
+        }
+        for at in (0..model.len() + 2).step_by(step) {
+            let line = newlines.partition_point(|&i| i < at.min(model.len()));
+            assert_eq!(table.byte_to_line(at), line, "byte_to_line({})", at);
+            let char = chars.partition_point(|&i| i < at.min(model.len()));
+            assert_eq!(table.byte_to_char(at), char, "byte_to_char({})", at);
+            assert_eq!(table.byte(at), model.get(at).copied(), "byte({})", at);
+        }
+        let (start, end) = (model.len() / 3, model.len() / 2);
+        assert_eq!(table.slice(start..end), &model[start..end]);
+    }
+
+    fn random_edits(original: Vec<u8>, edits: usize, seed: u32) {
+        // Bytes are inserted anywhere, so chars get cut in two and leave stray continuation bytes.
+        let pieces: [&[u8]; 8] = [b"a", b"\n", b"hello\nworld", "\u{e9}".as_bytes(), "\u{d55c}\n".as_bytes(), b"\x80", b"\r\n", b""];
+        let mut rng = Rng(seed);
+        let mut table = PieceTable::new(original.clone());
+        let mut model = original;
+        check(&table, &model);
+        for i in 0..edits {
+            if rng.below(3) == 0 && !model.is_empty() {
+                let start = rng.below(model.len() + 1);
+                let end = (start + rng.below(20)).min(model.len() + 5);
+                table.delete(start..end);
+                model.drain(start.min(model.len())..end.min(model.len()));
+            } else {
+                let at = rng.below(model.len() + 3);
+                let bytes = pieces[rng.below(pieces.len())];
+                table.insert(at, bytes);
+                let at = at.min(model.len());
+                model.splice(at..at, bytes.iter().copied());
+            }
+            if i % 10 == 0 || i == edits - 1 {
+                check(&table, &model);
+            } else {
+                assert_eq!(table.to_bytes(), model);
+            }
+        }
+    }
+
+    #[test]
+    fn edits_from_empty() {
+        random_edits(Vec::new(), 500, 0x1234_5678);
+    }
+
+    #[test]
+    fn edits_over_several_char_blocks() {
+        let line = "line with \u{e9} and \u{d55c}\n";
+        let original = line.repeat(3 * CHAR_BLOCK / line.len()).into_bytes();
+        random_edits(original, 200, 0x9e37_79b9);
+    }
+
+    #[test]
+    fn stray_continuation_bytes_are_no_chars() {
+        let table = PieceTable::new(b"a\x80\x80b\xffc".to_vec());
+        assert_eq!(table.char_count(), 4);
+        assert_eq!(table.char_to_byte(1), 3);
+        assert_eq!(table.byte_to_char(2), 1);
+    }
+}
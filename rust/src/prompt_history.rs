@@ -0,0 +1,157 @@
+//! What was answered to the prompts before, for Up and Down to bring back.
+//!
+//! Each kind of prompt keeps a history of its own, newest last, with an answer given again moved
+//! to the end rather than kept twice. It is saved as a text file of one answer per line, each
+//! after the name of its kind and a tab, oldest first.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::data;
+
+/// The most answers kept for each kind of prompt.
+const HISTORY_LEN: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PromptKind {
+    Search, // the pattern to find or replace
+    Replace, // what to replace it with
+    File, // a file name to save as or open
+    Command, // where to go: a point in the undo history or a buffer
+}
+
+impl PromptKind {
+    const ALL: [PromptKind; 4] = [PromptKind::Search, PromptKind::Replace, PromptKind::File, PromptKind::Command];
+
+    fn name(self) -> &'static str {
+        match self {
+            PromptKind::Search => "search",
+            PromptKind::Replace => "replace",
+            PromptKind::File => "file",
+            PromptKind::Command => "command",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PromptHistory {
+    entries: Vec<(PromptKind, String)>, // oldest first
+}
+
+impl PromptHistory {
+    /// Reads the history saved at `path`, skipping lines it can't make sense of.
+    /// A missing file is an empty history.
+    pub fn load(path: &Path) -> io::Result<PromptHistory> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let mut history = PromptHistory::default();
+        for line in text.lines() {
+            let (name, entry) = match line.split_once('\t') {
+                Some(split) => split,
+                None => continue,
+            };
+            if let Some(kind) = PromptKind::ALL.into_iter().find(|kind| kind.name() == name) {
+                history.add(kind, entry);
+            }
+        }
+        Ok(history)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::new();
+        for (kind, entry) in &self.entries {
+            text += &format!("{}\t{}\n", kind.name(), entry);
+        }
+        data::write(path, text.as_bytes())
+    }
+
+    /// Adds `entry` as the newest answer to prompts of `kind`, dropping the oldest past
+    /// [`HISTORY_LEN`]. Empty answers and answers over several lines aren't kept.
+    pub fn add(&mut self, kind: PromptKind, entry: &str) {
+        if entry.is_empty() || entry.contains(['\n', '\r']) {
+            return;
+        }
+        self.entries.retain(|(k, e)| *k != kind || e != entry);
+        self.entries.push((kind, entry.to_string()));
+        if self.len(kind) > HISTORY_LEN {
+            let oldest = self.entries.iter().position(|(k, _)| *k == kind);
+            if let Some(oldest) = oldest {
+                self.entries.remove(oldest);
+            }
+        }
+    }
+
+    /// Returns the answer to prompts of `kind` from `back` answers ago, 0 being the newest.
+    pub fn get(&self, kind: PromptKind, back: usize) -> Option<&str> {
+        let mut entries = self.entries.iter().rev().filter(|(k, _)| *k == kind);
+        entries.nth(back).map(|(_, entry)| entry.as_str())
+    }
+
+    pub fn len(&self, kind: PromptKind) -> usize {
+        self.entries.iter().filter(|(k, _)| *k == kind).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_given_again_move_to_the_end() {
+        let mut history = PromptHistory::default();
+        for (kind, entry) in [(PromptKind::Search, "foo"), (PromptKind::File, "a.txt"), (PromptKind::Search, "bar")] {
+            history.add(kind, entry);
+        }
+        history.add(PromptKind::Search, "foo");
+        assert_eq!((history.get(PromptKind::Search, 0), history.get(PromptKind::Search, 1)), (Some("foo"), Some("bar")));
+        assert_eq!((history.len(PromptKind::Search), history.get(PromptKind::Search, 2)), (2, None));
+        // The same answer to another kind of prompt is kept apart.
+        history.add(PromptKind::Replace, "foo");
+        assert_eq!((history.len(PromptKind::Search), history.len(PromptKind::Replace)), (2, 1));
+        for entry in ["", "two\nlines", "cr\r"] {
+            history.add(PromptKind::Search, entry);
+        }
+        assert_eq!(history.len(PromptKind::Search), 2);
+    }
+
+    #[test]
+    fn only_the_newest_answers_are_kept() {
+        let mut history = PromptHistory::default();
+        history.add(PromptKind::File, "kept");
+        for i in 0..HISTORY_LEN + 5 {
+            history.add(PromptKind::Search, &i.to_string());
+        }
+        assert_eq!(history.len(PromptKind::Search), HISTORY_LEN);
+        assert_eq!(history.get(PromptKind::Search, 0), Some(&*(HISTORY_LEN + 4).to_string()));
+        assert_eq!(history.get(PromptKind::Search, HISTORY_LEN - 1), Some("5"));
+        assert_eq!(history.get(PromptKind::File, 0), Some("kept"));
+    }
+
+    #[test]
+    fn saved_to_the_data_directory_and_loaded_back() {
+        let dir = std::env::temp_dir().join(format!("tiny-editor-{}-data", std::process::id()));
+        // No other test reads `XDG_DATA_HOME`.
+        std::env::set_var("XDG_DATA_HOME", &dir);
+        let path = data::prompt_history_file().unwrap();
+        assert_eq!(path, dir.join("tiny-editor/history"));
+        assert_eq!(PromptHistory::load(&path).unwrap().len(PromptKind::Search), 0);
+
+        let mut history = PromptHistory::default();
+        history.add(PromptKind::Search, "a\tb");
+        history.add(PromptKind::Command, "earlier 5m");
+        history.add(PromptKind::Search, "c");
+        history.save(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "search\ta\tb\ncommand\tearlier 5m\nsearch\tc\n");
+
+        // Lines it can't make sense of are skipped.
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "no tab\nother\tx\n").unwrap();
+        let loaded = PromptHistory::load(&path).unwrap();
+        assert_eq!((loaded.get(PromptKind::Search, 0), loaded.get(PromptKind::Search, 1)), (Some("c"), Some("a\tb")));
+        assert_eq!((loaded.len(PromptKind::Search), loaded.get(PromptKind::Command, 0)), (2, Some("earlier 5m")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
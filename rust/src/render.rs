@@ -0,0 +1,395 @@
+//! Drawing the editor into a frame of escape sequences, the equivalent of `editorRefreshScreen`.
+
+use std::ops::Range;
+
+use crate::buffer::Buffer;
+use crate::cursor::Cursor;
+use crate::editor::Editor;
+use crate::row::{str_width, truncate_to_width, Row};
+use crate::syntax::{syntax_to_color, Highlight};
+use crate::window::Rect;
+use crate::TINY_VERSION;
+
+/// A window as it is drawn: the buffer it shows, where it is in it and where it goes on the screen.
+struct Pane<'a> {
+    buffer: &'a Buffer,
+    index: usize, // of the buffer in the buffer list
+    cursor: Cursor,
+    rowoff: usize,
+    coloff: usize,
+    rect: Rect, // text and status bar
+    focused: bool, // only the focused window shows the selection, the other cursors and the match
+}
+
+impl Pane<'_> {
+    fn textrows(&self) -> usize {
+        self.rect.rows.saturating_sub(1)
+    }
+}
+
+/// Returns the escape sequence that moves the cursor to a cell, counted from 0.
+fn move_to(row: usize, col: usize) -> Vec<u8> {
+    format!("\x1b[{};{}H", row + 1, col + 1).into_bytes()
+}
+
+/// Returns the `render` indices of `row`, row `filerow` of the buffer, to highlight as one of
+/// `matches`. A match over several rows takes the end of its first row, the rows in between and
+/// the start of its last row.
+fn matches_on_row(matches: &[(Cursor, Cursor)], filerow: usize, row: &Row) -> Vec<Range<usize>> {
+    let first = matches.partition_point(|(_, end)| end.cy < filerow);
+    matches[first..]
+        .iter()
+        .take_while(|(start, _)| start.cy <= filerow)
+        .map(|(start, end)| {
+            let from = if filerow == start.cy { row.cx_to_render(start.cx) } else { 0 };
+            let to = if filerow == end.cy { row.cx_to_render(end.cx) } else { row.render.len() };
+            from..to
+        })
+        .collect()
+}
+
+impl Editor {
+    fn scroll(&mut self) {
+        self.rx = 0;
+        if let Some(row) = self.buffer.plain_row(self.cursor.cy) {
+            self.rx = row.cx_to_rx(self.cursor.cx);
+        }
+
+        if self.cursor.cy < self.rowoff {
+            self.rowoff = self.cursor.cy; // scroll up
+        }
+        if self.cursor.cy >= self.rowoff + self.screenrows {
+            self.rowoff = self.cursor.cy + 1 - self.screenrows; // scroll down
+        }
+        if self.rx < self.coloff {
+            self.coloff = self.rx; // scroll left
+        }
+        if self.rx >= self.coloff + self.screencols {
+            self.coloff = self.rx + 1 - self.screencols; // scroll right
+        }
+    }
+
+    /// Returns the screen columns of row `filerow` that are selected.
+    fn selection_on_row(&self, filerow: usize) -> Option<Range<usize>> {
+        if let Some(block) = self.block {
+            let columns = block.columns();
+            return match block.rows().contains(&filerow) {
+                // A block with no columns is a column to type into, drawn one cell wide.
+                true if columns.is_empty() => Some(columns.start..columns.start + 1),
+                true => Some(columns),
+                false => None,
+            };
+        }
+        let (start, end) = self.selection()?;
+        if filerow < start.cy || filerow > end.cy {
+            return None;
+        }
+        let row = self.buffer.plain_row(filerow)?;
+        let from = if filerow == start.cy { row.cx_to_rx(start.cx) } else { 0 };
+        let to = if filerow == end.cy { row.cx_to_rx(end.cx) } else { usize::MAX };
+        Some(from..to)
+    }
+
+    /// Returns the screen columns of the cursors besides the main one on row `filerow`.
+    fn cursors_on_row(&self, filerow: usize, row: &Row) -> Vec<usize> {
+        self.others
+            .iter()
+            .filter(|cursor| cursor.cy == filerow)
+            .map(|cursor| row.cx_to_rx(cursor.cx))
+            .collect()
+    }
+
+    fn draw_rows(&self, ab: &mut Vec<u8>, pane: &Pane) {
+        let cols = pane.rect.cols;
+        // Matches of the search are drawn over the syntax highlighting, which is left as it is.
+        let visible = match pane.index == self.current {
+            true => self.visible_matches(pane.rowoff..pane.rowoff + pane.textrows()),
+            false => Vec::new(),
+        };
+        for y in 0..pane.textrows() {
+            ab.extend_from_slice(&move_to(pane.rect.top + y, pane.rect.left));
+            let filerow = y + pane.rowoff;
+            let drawn = match pane.buffer.row(filerow) {
+                None if pane.buffer.numrows() == 0 && !pane.buffer.is_loading() && y == pane.textrows() / 3 => {
+                    let welcome = format!("TINY editor -- version {}", TINY_VERSION);
+                    let welcome = truncate_to_width(&welcome, cols); // truncate welcome message if it is too long
+                    let mut padding = (cols - str_width(welcome)) / 2; // center welcome message
+                    let drawn = padding + str_width(welcome);
+                    if padding > 0 {
+                        ab.push(b'~');
+                        padding -= 1;
+                    }
+                    ab.extend(std::iter::repeat_n(b' ', padding));
+                    ab.extend_from_slice(welcome.as_bytes());
+                    drawn
+                }
+                None => {
+                    ab.push(b'~');
+                    1
+                }
+                Some(row) => {
+                    let matches = matches_on_row(&visible, filerow, &row);
+                    let (left, right) = (pane.coloff, pane.coloff + cols); // visible columns
+                    let (selected, cursors) = match pane.focused {
+                        true => (self.selection_on_row(filerow), self.cursors_on_row(filerow, &row)),
+                        false => (None, Vec::new()),
+                    };
+                    let mut current_color = None;
+                    let mut inverted = false;
+                    for cell in row.cells() {
+                        if cell.rx + cell.width <= left {
+                            continue;
+                        }
+                        if cell.rx >= right {
+                            break;
+                        }
+
+                        // The other cursors are drawn like a selection one cell wide.
+                        let in_selection = selected.as_ref().is_some_and(|range| range.contains(&cell.rx))
+                            || cursors.contains(&cell.rx);
+                        if in_selection != inverted {
+                            inverted = in_selection;
+                            // reverse video on top of whatever color the syntax gives (27; Reverse Video off)
+                            ab.extend_from_slice(if inverted { b"\x1b[7m" } else { b"\x1b[27m" });
+                        }
+
+                        let hl = match matches.iter().any(|range| range.contains(&cell.render)) {
+                            true => Highlight::Match,
+                            false => row.hl[cell.render],
+                        };
+                        let cluster = &row.chars[cell.cx..cell.cx + cell.len];
+                        let color = (hl != Highlight::Normal).then(|| syntax_to_color(hl));
+                        if current_color != color {
+                            match color {
+                                Some(color) => ab.extend_from_slice(format!("\x1b[{}m", color).as_bytes()),
+                                None => ab.extend_from_slice(b"\x1b[39m"), // reset color
+                            }
+                            current_color = color;
+                        }
+
+                        if cluster == b"\t" || cell.rx < left || cell.rx + cell.width > right {
+                            // the blanks of a tab, or a wide character cut in half by the edge of the screen
+                            let shown = (cell.rx + cell.width).min(right) - cell.rx.max(left);
+                            ab.extend(std::iter::repeat_n(b' ', shown));
+                        } else if cluster[0].is_ascii_control() || std::str::from_utf8(cluster).is_err() {
+                            // In ASCII, the capital letters of the alphabet come right after '@'.
+                            let c = cluster[0];
+                            let sym = if c <= 26 { b'@' + c } else { b'?' };
+                            ab.extend_from_slice(b"\x1b[7m"); // invert colors (7; Reverse Video)
+                            ab.push(sym);
+                            ab.extend_from_slice(b"\x1b[m"); // reset colors (m; Turn Off Character Attributes)
+                            if let Some(color) = current_color {
+                                ab.extend_from_slice(format!("\x1b[{}m", color).as_bytes());
+                            }
+                            if inverted {
+                                ab.extend_from_slice(b"\x1b[7m");
+                            }
+                        } else {
+                            ab.extend_from_slice(cluster);
+                        }
+                    }
+                    ab.extend_from_slice(b"\x1b[39m"); // reset color
+                    let end = row.cx_to_rx(row.len());
+                    let mut drawn = end.clamp(left, right) - left;
+                    if cursors.contains(&end) && (left..right).contains(&end) {
+                        // a cursor at the end of the row, on the blank after it
+                        if !inverted {
+                            ab.extend_from_slice(b"\x1b[7m");
+                            inverted = true;
+                        }
+                        ab.push(b' ');
+                        drawn += 1;
+                    }
+                    if inverted {
+                        ab.extend_from_slice(b"\x1b[27m");
+                    }
+                    drawn
+                }
+            };
+
+            // Blank out the rest of the window's row, but not the window beside it.
+            ab.extend(std::iter::repeat_n(b' ', cols.saturating_sub(drawn)));
+        }
+    }
+
+    fn draw_status_bar(&self, ab: &mut Vec<u8>, pane: &Pane) {
+        let cols = pane.rect.cols;
+        ab.extend_from_slice(&move_to(pane.rect.top + pane.textrows(), pane.rect.left));
+        ab.extend_from_slice(b"\x1b[7m"); // invert colors (7; Reverse Video)
+        if pane.focused && self.windows.len() > 1 {
+            ab.extend_from_slice(b"\x1b[1m"); // the focused window stands out in bold (1; Bold)
+        }
+        let state = match pane.buffer.loading_progress() {
+            Some(percent) => format!("(loading {}%)", percent),
+            None if pane.buffer.is_dirty() => "(modified)".to_string(),
+            None => String::new(),
+        };
+        let cursors = match self.others.len() {
+            n if n > 0 && pane.focused => format!(" [{} cursors]", n + 1),
+            _ => String::new(),
+        };
+        let position = match self.buffer_count() {
+            1 => String::new(),
+            n => format!(" [{}/{}]", pane.index + 1, n),
+        };
+        let status = format!(
+            "{:.20}{} - {} lines {}{}",
+            pane.buffer.filename().unwrap_or("[No Name]"),
+            position,
+            pane.buffer.numrows(),
+            state,
+            cursors
+        );
+        // Where we are in the undo tree, and which branch redo takes if there is more than one.
+        let history = pane.buffer.history();
+        let undo = match history.branch() {
+            _ if history.last_state() == 0 => String::new(),
+            Some((branch, count)) => {
+                format!("undo {}/{} branch {}/{} | ", history.state(), history.last_state(), branch, count)
+            }
+            None => format!("undo {}/{} | ", history.state(), history.last_state()),
+        };
+        let matches = match self.match_count() {
+            Some((Some(at), count)) if pane.focused => format!("match {} of {} | ", at, count),
+            Some((None, 1)) if pane.focused => "1 match | ".to_string(),
+            Some((None, count)) if pane.focused => format!("{} matches | ", count),
+            _ => String::new(),
+        };
+        let rstatus = format!(
+            "{}{}{} | {}/{}",
+            matches,
+            undo,
+            pane.buffer.syntax().map_or("no ft", |s| s.filetype),
+            pane.cursor.cy + 1,
+            pane.buffer.numrows()
+        );
+        let status = truncate_to_width(&status, cols); // truncate status message if it is too long
+        ab.extend_from_slice(status.as_bytes());
+
+        let mut len = str_width(status);
+        while len < cols {
+            if cols - len == str_width(&rstatus) {
+                ab.extend_from_slice(rstatus.as_bytes());
+                break;
+            }
+            ab.push(b' ');
+            len += 1;
+        }
+        ab.extend_from_slice(b"\x1b[m"); // reset colors
+    }
+
+    /// Draws the lines between windows side by side.
+    fn draw_separators(&self, ab: &mut Vec<u8>) {
+        for separator in self.layout.separators(self.area) {
+            for y in 0..separator.rows {
+                ab.extend_from_slice(&move_to(separator.top + y, separator.left));
+                ab.extend_from_slice("│".as_bytes());
+            }
+        }
+    }
+
+    /// Draws the tab line, with the current buffer's tab the other way round from the rest.
+    fn draw_tab_line(&self, ab: &mut Vec<u8>) {
+        ab.extend_from_slice(&move_to(0, 0));
+        ab.extend_from_slice(b"\x1b[7m"); // invert colors (7; Reverse Video)
+        let mut len = 0;
+        for (index, label, columns) in self.tabs() {
+            if index == self.current {
+                ab.extend_from_slice(b"\x1b[1;27m"); // bold, not inverted (27; Reverse Video off)
+            }
+            let label = truncate_to_width(&label, columns.len());
+            ab.extend_from_slice(label.as_bytes());
+            len += str_width(label);
+            if index == self.current {
+                ab.extend_from_slice(b"\x1b[22;7m"); // back to the rest (22; Bold off)
+            }
+        }
+        ab.extend(std::iter::repeat_n(b' ', self.area.cols.saturating_sub(len)));
+        ab.extend_from_slice(b"\x1b[m"); // reset colors
+    }
+
+    fn draw_message_bar(&self, ab: &mut Vec<u8>) {
+        ab.extend_from_slice(&move_to(self.area.top + self.area.rows, 0));
+        ab.extend_from_slice(b"\x1b[K");
+        let msg = truncate_to_width(&self.statusmsg, self.area.cols); // truncate message if it is too long
+        if !msg.is_empty() && self.statusmsg_time.elapsed().as_secs() < 5 {
+            ab.extend_from_slice(msg.as_bytes()); // display message for 5 seconds
+        }
+    }
+
+    /// Scrolls the cursor into view and returns the escape sequences that redraw the whole screen.
+    pub fn refresh_screen(&mut self) -> Vec<u8> {
+        self.scroll();
+        self.update_matches();
+
+        // Sequences for the terminal itself, like OSC 52 to set the clipboard, go first.
+        let mut ab = std::mem::take(&mut self.terminal_out);
+
+        ab.extend_from_slice(b"\x1b[?25l"); // hide cursor (l; Reset Mode)
+
+        if self.tab_line {
+            self.draw_tab_line(&mut ab);
+        }
+        for (index, rect) in self.layout.windows(self.area) {
+            let window = self.window(index);
+            let pane = Pane {
+                buffer: self.buffer_at(window.buffer),
+                index: window.buffer,
+                cursor: window.cursor,
+                rowoff: window.rowoff,
+                coloff: window.coloff,
+                rect,
+                focused: index == self.focused,
+            };
+            self.draw_rows(&mut ab, &pane);
+            self.draw_status_bar(&mut ab, &pane);
+        }
+        self.draw_separators(&mut ab);
+        self.draw_message_bar(&mut ab);
+
+        let rect = self.window_rect(self.focused);
+        ab.extend_from_slice(&move_to(
+            rect.top + (self.cursor.cy - self.rowoff),
+            rect.left + (self.rx - self.coloff),
+        ));
+
+        ab.extend_from_slice(b"\x1b[?25h"); // show cursor (h; Set Mode)
+        ab
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::{syntax_to_color, Highlight};
+    use crate::{Cursor, Editor, Event, Key, KeyCode};
+
+    #[test]
+    fn screens_with_no_room_for_text() {
+        // The last one has a tab line, which leaves no row for text on a screen of three.
+        for (rows, cols, tab_line) in [(2, 20, false), (1, 1, false), (0, 0, false), (3, 20, true)] {
+            let mut editor = Editor::new(rows, cols);
+            editor.set_tab_line(tab_line);
+            for code in [KeyCode::Char('a'), KeyCode::Char('b'), KeyCode::Enter, KeyCode::Char('c')] {
+                editor.process_key(Key::from(code));
+                editor.refresh_screen();
+            }
+            assert_eq!(editor.cursor(), Cursor::new(1, 1));
+        }
+    }
+
+    #[test]
+    fn every_visible_match_is_drawn() {
+        let mut editor = Editor::new(10, 80);
+        editor.process_event(Event::Paste("ab ab\nab\nxab\n".repeat(10)));
+        editor.process_key(Key::ctrl('f'));
+        for c in "ab".chars() {
+            editor.process_key(Key::from(KeyCode::Char(c)));
+        }
+        editor.process_key(Key::from(KeyCode::Enter));
+        let screen = String::from_utf8(editor.refresh_screen()).unwrap();
+        let color = syntax_to_color(Highlight::Match);
+        let drawn = screen.matches(&format!("\x1b[{}mab\x1b[39m", color)).count();
+        assert_eq!(drawn, 4 + 4 + 3); // the 8 rows of text on the screen
+        assert!(screen.contains("match 1 of 40 | "), "{:?}", screen);
+    }
+}
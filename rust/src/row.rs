@@ -0,0 +1,243 @@
+//! A single line of the buffer, the equivalent of `erow` in the C editor.
+//!
+//! `chars` holds the bytes of the line, and `cx` indexes into them. On screen a line is a
+//! sequence of grapheme clusters (what the user thinks of as one character, like `é` written
+//! as `e` plus a combining accent), each one, two or, for a tab, up to `TAB_STOP` cells wide.
+//! [`Cell`] ties the two together, and `rx` counts cells.
+
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::syntax::Highlight;
+use crate::TAB_STOP;
+
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub(crate) chars: Vec<u8>,
+    pub(crate) render: Vec<u8>, // render string
+    pub(crate) hl: Vec<Highlight>, // highlight
+    pub(crate) hl_open_comment: bool, // highlight open comment
+    cells: Vec<Cell>, // the grapheme clusters of `chars`, from `update_render`
+}
+
+/// One grapheme cluster of a row and where it ends up on screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub cx: usize, // where it starts in `chars`
+    pub len: usize, // how many bytes of `chars` it takes
+    pub rx: usize, // the screen column it starts at
+    pub width: usize, // how many columns it takes
+    pub render: usize, // where it starts in `render`
+}
+
+impl Row {
+    pub(crate) fn new(chars: &[u8]) -> Row {
+        let mut row = Row {
+            chars: chars.to_vec(),
+            render: Vec::new(),
+            hl: Vec::new(),
+            hl_open_comment: false,
+            cells: Vec::new(),
+        };
+        row.update_render();
+        row
+    }
+
+    pub fn chars(&self) -> &[u8] {
+        &self.chars
+    }
+
+    pub fn render(&self) -> &[u8] {
+        &self.render
+    }
+
+    pub fn hl(&self) -> &[Highlight] {
+        &self.hl
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// Returns the grapheme clusters of the row, in order.
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /// Returns the index in `cells` of the cluster `cx` is in, or `cells.len()` past the end.
+    fn cell_at(&self, cx: usize) -> usize {
+        self.cells.partition_point(|cell| cell.cx + cell.len <= cx)
+    }
+
+    /// Converts a `chars` index into a screen column, expanding tabs and counting wide characters twice.
+    pub fn cx_to_rx(&self, cx: usize) -> usize {
+        match self.cells.get(self.cell_at(cx)) {
+            Some(cell) => cell.rx,
+            None => self.cells.last().map_or(0, |cell| cell.rx + cell.width),
+        }
+    }
+
+    /// Converts a screen column back into a `chars` index, the start of the cluster drawn there.
+    pub fn rx_to_cx(&self, rx: usize) -> usize {
+        let i = self.cells.partition_point(|cell| cell.rx + cell.width <= rx);
+        self.cells.get(i).map_or(self.chars.len(), |cell| cell.cx)
+    }
+
+    /// Converts a `chars` index into a `render` index.
+    pub fn cx_to_render(&self, cx: usize) -> usize {
+        self.cells.get(self.cell_at(cx)).map_or(self.render.len(), |cell| cell.render)
+    }
+
+    /// Converts a range of screen columns into the `chars` of the clusters that start in it.
+    pub fn columns_to_cx(&self, columns: Range<usize>) -> Range<usize> {
+        let cx = |rx| {
+            let i = self.cells.partition_point(|cell| cell.rx < rx);
+            self.cells.get(i).map_or(self.chars.len(), |cell| cell.cx)
+        };
+        cx(columns.start)..cx(columns.end)
+    }
+
+    /// Returns the start of the cluster before `cx`.
+    pub fn prev_boundary(&self, cx: usize) -> usize {
+        let i = self.cells.partition_point(|cell| cell.cx < cx);
+        i.checked_sub(1).map_or(0, |i| self.cells[i].cx)
+    }
+
+    /// Returns the end of the cluster `cx` is in.
+    pub fn next_boundary(&self, cx: usize) -> usize {
+        self.cells.get(self.cell_at(cx)).map_or(self.chars.len(), |cell| cell.cx + cell.len)
+    }
+
+    /// Returns the start of the cluster `cx` is in, so a cursor never ends up inside one.
+    pub fn floor_boundary(&self, cx: usize) -> usize {
+        self.cells.get(self.cell_at(cx)).map_or(self.chars.len(), |cell| cell.cx)
+    }
+
+    /// Rebuilds `render` and `cells` from `chars`. The caller is responsible for re-highlighting
+    /// the row.
+    pub(crate) fn update_render(&mut self) {
+        self.cells = split_cells(&self.chars);
+        self.render.clear();
+        for cell in &self.cells {
+            if self.chars[cell.cx] == b'\t' {
+                self.render.extend(std::iter::repeat_n(b' ', cell.width));
+            } else {
+                self.render.extend_from_slice(&self.chars[cell.cx..cell.cx + cell.len]);
+            }
+        }
+        self.hl = vec![Highlight::Normal; self.render.len()];
+    }
+}
+
+/// Splits `chars` into grapheme clusters, in order.
+///
+/// A tab is as wide as it takes to get to the next tab stop, control characters are one column
+/// wide since they are drawn as `^X`, and so is every byte that isn't valid UTF-8.
+fn split_cells(chars: &[u8]) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    let (mut rx, mut render) = (0, 0);
+    let mut push = |cx: usize, cluster: &[u8], width: usize| {
+        // `rx % TAB_STOP` is how many columns we are to the right of the last tab stop.
+        let width = if cluster == b"\t" { TAB_STOP - (rx % TAB_STOP) } else { width };
+        let len = cluster.len();
+        cells.push(Cell { cx, len, rx, width, render });
+        rx += width;
+        render += if cluster == b"\t" { width } else { len };
+    };
+
+    let mut cx = 0;
+    for chunk in chars.utf8_chunks() {
+        for (i, grapheme) in chunk.valid().grapheme_indices(true) {
+            push(cx + i, grapheme.as_bytes(), grapheme_width(grapheme));
+        }
+        cx += chunk.valid().len();
+        for i in 0..chunk.invalid().len() {
+            push(cx + i, &chunk.invalid()[i..i + 1], 1);
+        }
+        cx += chunk.invalid().len();
+    }
+    cells
+}
+
+/// Returns how many columns `grapheme` takes on screen.
+fn grapheme_width(grapheme: &str) -> usize {
+    if grapheme.starts_with(|c: char| c.is_ascii_control()) {
+        1 // drawn as ^X
+    } else {
+        grapheme.width()
+    }
+}
+
+/// Returns how many columns `s` takes on screen.
+pub fn str_width(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Returns the longest prefix of `s` that fits in `cols` columns.
+pub fn truncate_to_width(s: &str, cols: usize) -> &str {
+    let mut width = 0;
+    for (i, grapheme) in s.grapheme_indices(true) {
+        width += grapheme_width(grapheme);
+        if width > cols {
+            return &s[..i];
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabs_go_to_the_next_tab_stop() {
+        let row = Row::new(b"a\tb\t");
+        assert_eq!(row.render(), format!("a{}b{}", " ".repeat(TAB_STOP - 1), " ".repeat(TAB_STOP - 1)).as_bytes());
+        assert_eq!((row.cx_to_rx(1), row.cx_to_rx(2), row.cx_to_rx(4)), (1, TAB_STOP, 2 * TAB_STOP));
+        // Every column of the tab's blanks is the tab itself.
+        assert_eq!((row.rx_to_cx(1), row.rx_to_cx(TAB_STOP - 1), row.rx_to_cx(TAB_STOP)), (1, 1, 2));
+        assert_eq!(row.cx_to_render(2), TAB_STOP);
+        assert_eq!(row.columns_to_cx(2..TAB_STOP + 1), 2..3);
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        let row = Row::new("中a😀".as_bytes());
+        let widths: Vec<_> = row.cells().iter().map(|cell| (cell.cx, cell.rx, cell.width)).collect();
+        assert_eq!(widths, [(0, 0, 2), (3, 2, 1), (4, 3, 2)]);
+        assert_eq!(row.cx_to_rx(row.len()), 5);
+        // A column inside a wide cell is the character drawn over it.
+        assert_eq!((row.rx_to_cx(1), row.rx_to_cx(4), row.rx_to_cx(5)), (0, 4, 8));
+        assert_eq!(row.columns_to_cx(1..4), 3..8);
+        assert_eq!((row.next_boundary(0), row.prev_boundary(8)), (3, 4));
+    }
+
+    #[test]
+    fn clusters_of_several_chars_are_one_cell() {
+        // `e` and a combining accent, and a family emoji of three people joined by ZWJs.
+        let (accent, family) = ("e\u{301}", "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}");
+        let row = Row::new(format!("{}{}x", accent, family).as_bytes());
+        let (after_accent, after_family) = (accent.len(), accent.len() + family.len());
+        assert_eq!(row.cells().len(), 3);
+        assert_eq!(row.next_boundary(0), after_accent);
+        assert_eq!(row.next_boundary(after_accent + 1), after_family);
+        assert_eq!(row.prev_boundary(after_family), after_accent);
+        assert_eq!((row.floor_boundary(1), row.floor_boundary(after_family - 1)), (0, after_accent));
+        assert_eq!((row.cx_to_rx(after_accent), row.cx_to_rx(after_family)), (1, 3));
+        assert_eq!(row.rx_to_cx(2), after_accent);
+    }
+
+    #[test]
+    fn control_characters_and_invalid_bytes_are_one_column() {
+        let row = Row::new(b"\x01\xffa");
+        let widths: Vec<_> = row.cells().iter().map(|cell| (cell.cx, cell.width)).collect();
+        assert_eq!(widths, [(0, 1), (1, 1), (2, 1)]);
+        assert_eq!((row.prev_boundary(0), row.next_boundary(3), row.floor_boundary(9)), (0, 3, 3));
+    }
+}
@@ -0,0 +1,349 @@
+//! Finding text in a buffer: literally or by regular expression, with or without regard to case,
+//! anywhere or as a whole word.
+//!
+//! Every kind of search goes through a [`regex::bytes::Regex`], with a literal pattern escaped
+//! first, and matches against the text of the rows as it is in the file (tabs and all), not
+//! as it is drawn.
+//!
+//! Only `\n` written in the pattern takes a match from one row to the next, and only over as
+//! many line breaks as the pattern has `\n`s: a match starting on a row is looked for in that
+//! row and as many after it, each ending in `\n` whether the file uses `\n` or `\r\n`. A pattern
+//! without `\n` is matched one row at a time, where there is no line break for `\s`, `[^x]` or
+//! `\x0a` to match.
+
+use std::ops::Range;
+
+use regex::bytes::{Regex, RegexBuilder};
+
+use crate::buffer::Buffer;
+use crate::cursor::Cursor;
+
+/// How a pattern is matched, toggled from the search prompt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub regex: bool, // the pattern is a regular expression rather than text to find as it is
+    pub ignore_case: bool,
+    pub whole_word: bool, // only matches with no word characters right before or after them
+}
+
+impl SearchOptions {
+    /// Describes the options that are on, like `regex, ignore case`, for the prompt.
+    pub fn describe(&self) -> String {
+        let on = [(self.regex, "regex"), (self.ignore_case, "ignore case"), (self.whole_word, "whole word")];
+        let on: Vec<&str> = on.iter().filter(|(on, _)| *on).map(|(_, name)| *name).collect();
+        on.join(", ")
+    }
+}
+
+/// A pattern ready to search with.
+#[derive(Clone, Debug)]
+pub struct Matcher {
+    regex: Regex,
+    options: SearchOptions,
+    lines: usize, // the `\n`s in the pattern, as many line breaks as a match can run over
+}
+
+/// The text a match that starts on row `cy` is looked for in, see [`Matcher::snippet`].
+struct Snippet {
+    text: Vec<u8>,
+    cy: usize,
+    starts: Vec<usize>, // where each row starts in `text`, and after a line break the end of it
+    first_end: usize, // the offsets of `text` on row `cy`, its line break included
+}
+
+impl Snippet {
+    fn cursor(&self, at: usize) -> Cursor {
+        let i = self.starts.partition_point(|&start| start <= at).saturating_sub(1);
+        Cursor::new(at - self.starts[i], self.cy + i)
+    }
+
+    fn cursors(&self, range: Range<usize>) -> (Cursor, Cursor) {
+        (self.cursor(range.start), self.cursor(range.end))
+    }
+
+    fn offset(&self, at: Cursor) -> Option<usize> {
+        Some(self.starts.get(at.cy.checked_sub(self.cy)?)? + at.cx)
+    }
+}
+
+impl Matcher {
+    /// Returns an error if `options.regex` is set and `pattern` isn't a valid regular expression.
+    /// Outside of a regex, `\n` in `pattern` stands for a line break and `\\` for a backslash.
+    pub fn new(pattern: &str, options: SearchOptions) -> Result<Matcher, regex::Error> {
+        let (pattern, lines) = match options.regex {
+            true => (pattern.to_string(), line_breaks(pattern)),
+            false => {
+                let pattern = unescape(pattern);
+                (regex::escape(&pattern), pattern.matches('\n').count())
+            }
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(options.ignore_case)
+            .multi_line(lines > 0) // `^` and `$` at the start and end of every row
+            .build()?;
+        Ok(Matcher { regex, options, lines })
+    }
+
+    /// Returns the first match in `text` that starts at `at` or after. Empty matches don't count,
+    /// there being nothing to show or select.
+    pub fn find_at(&self, text: &[u8], mut at: usize) -> Option<Range<usize>> {
+        while at <= text.len() {
+            let found = self.regex.find_at(text, at)?.range();
+            if !found.is_empty() && (!self.options.whole_word || is_whole_word(text, &found)) {
+                return Some(found);
+            }
+            at = found.start + 1;
+        }
+        None
+    }
+
+    /// Returns every match in `text`, in order and not overlapping.
+    pub fn find_all(&self, text: &[u8]) -> Vec<Range<usize>> {
+        let mut found = Vec::new();
+        let mut at = 0;
+        while let Some(range) = self.find_at(text, at) {
+            at = range.end;
+            found.push(range);
+        }
+        found
+    }
+
+    /// Returns the text a match that starts on row `cy` of `buffer` is looked for in: the row,
+    /// or for a pattern with `\n`s the row and as many after it, each followed by `\n`.
+    fn snippet(&self, buffer: &Buffer, cy: usize) -> Option<Snippet> {
+        let mut text = buffer.row_chars(cy)?;
+        if self.lines == 0 {
+            let first_end = text.len();
+            return Some(Snippet { text, cy, starts: vec![0], first_end });
+        }
+        text.push(b'\n');
+        let mut starts = vec![0, text.len()];
+        for row in cy + 1..(cy + 1 + self.lines).min(buffer.numrows()) {
+            text.extend(buffer.row_chars(row)?);
+            text.push(b'\n');
+            starts.push(text.len());
+        }
+        Some(Snippet { text, cy, first_end: starts[1], starts })
+    }
+
+    /// Returns every match in `buffer`, in order, as the cursors at their start and end.
+    pub fn find_everywhere(&self, buffer: &Buffer) -> Vec<(Cursor, Cursor)> {
+        self.find_in_rows(buffer, 0..buffer.numrows())
+    }
+
+    /// Returns the matches in `buffer` that are on `rows`, in part at least, in order.
+    pub fn find_in_rows(&self, buffer: &Buffer, rows: Range<usize>) -> Vec<(Cursor, Cursor)> {
+        let rows = rows.start..rows.end.min(buffer.numrows());
+        let mut found = Vec::new();
+        let mut last_end = Cursor::default();
+        // Matches from up to `lines` rows above can reach down into `rows`.
+        for cy in rows.start.saturating_sub(self.lines)..rows.end {
+            let snippet = match self.snippet(buffer, cy) {
+                Some(snippet) => snippet,
+                None => break,
+            };
+            for range in self.find_all(&snippet.text) {
+                if range.start >= snippet.first_end {
+                    break; // found again from the row it starts on
+                }
+                let (start, end) = snippet.cursors(range);
+                if start < last_end {
+                    continue; // overlaps the match before it
+                }
+                last_end = end;
+                if end.cy >= rows.start {
+                    found.push((start, end));
+                }
+            }
+        }
+        found
+    }
+
+    /// Returns the first match in `buffer` at `from` or after it, as the cursors at its start and
+    /// end. Past the last row it goes on from the top if `wrap` is set, up to `from` again.
+    pub fn find_forward(&self, buffer: &Buffer, from: Cursor, wrap: bool) -> Option<(Cursor, Cursor)> {
+        let numrows = buffer.numrows();
+        if numrows == 0 {
+            return None;
+        }
+        let from = match from.cy < numrows {
+            true => from,
+            false if wrap => Cursor::default(),
+            false => return None,
+        };
+        let rows = if wrap { numrows + 1 } else { numrows - from.cy };
+        for i in 0..rows {
+            let cy = (from.cy + i) % numrows;
+            let snippet = self.snippet(buffer, cy)?;
+            let at = if i == 0 { from.cx.min(snippet.first_end) } else { 0 };
+            match self.find_at(&snippet.text, at) {
+                Some(range) if range.start >= snippet.first_end => {} // found from the row it starts on
+                // Back on the first row, only what comes before `from` hasn't been seen yet.
+                Some(range) if i == numrows && range.start >= from.cx => return None,
+                Some(range) => return Some(snippet.cursors(range)),
+                None => {}
+            }
+        }
+        None
+    }
+
+    /// Returns the last match in `buffer` that starts before `from`, going on from the bottom
+    /// past the first row, up to `from` again.
+    pub fn find_backward(&self, buffer: &Buffer, from: Cursor) -> Option<(Cursor, Cursor)> {
+        let numrows = buffer.numrows();
+        if numrows == 0 {
+            return None;
+        }
+        let from = match from.cy < numrows {
+            true => from,
+            false => Cursor::new(usize::MAX, numrows - 1), // past the end, after everything
+        };
+        for i in 0..=numrows {
+            let cy = (from.cy + numrows - i % numrows) % numrows;
+            let snippet = self.snippet(buffer, cy)?;
+            let unseen = |range: &Range<usize>| match i {
+                0 => range.start < from.cx,
+                i if i == numrows => range.start >= from.cx,
+                _ => true,
+            };
+            let on_row = self.find_all(&snippet.text).into_iter().filter(|range| range.start < snippet.first_end);
+            if let Some(range) = on_row.rev().find(unseen) {
+                return Some(snippet.cursors(range));
+            }
+        }
+        None
+    }
+
+    /// Returns what the match from `start` to `end` in `buffer` is replaced with. `\n` in `with`
+    /// stands for a line break and `\\` for a backslash. In a regex, `$1` or `${1}` stands for what
+    /// the first group matched, `$name` for a named group, and `$$` for `$`.
+    pub fn replacement(&self, buffer: &Buffer, (start, end): (Cursor, Cursor), with: &str) -> Vec<u8> {
+        let with = unescape(with);
+        if !self.options.regex {
+            return with.into_bytes();
+        }
+        // The match is found again in the same text it was found in, for its groups.
+        let snippet = self.snippet(buffer, start.cy);
+        let found = snippet.as_ref().and_then(|snippet| Some((snippet, snippet.offset(start)?..snippet.offset(end)?)));
+        let mut out = Vec::new();
+        let captures = found.and_then(|(snippet, range)| {
+            let captures = self.regex.captures_at(&snippet.text, range.start)?;
+            captures.get(0).is_some_and(|m| m.range() == range).then_some(captures)
+        });
+        match captures {
+            Some(captures) => captures.expand(with.as_bytes(), &mut out),
+            None => out.extend_from_slice(with.as_bytes()),
+        }
+        out
+    }
+}
+
+/// Counts the `\n`s in a regular expression, skipping `\\n`, which is a backslash and an `n`.
+fn line_breaks(pattern: &str) -> usize {
+    let mut count = 0;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.next() == Some('n') {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Turns `\n` into a line break and `\\` into a backslash, leaving any other backslash as it is.
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('\\') => out.push('\\'),
+                Some(c) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                None => out.push('\\'),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Whether `range` of `text` has no word characters right before or right after it.
+fn is_whole_word(text: &[u8], range: &Range<usize>) -> bool {
+    (range.start == 0 || !is_word_byte(text[range.start - 1])) && text.get(range.end).is_none_or(|&c| !is_word_byte(c))
+}
+
+/// Whether `c` can be part of a word: letters, digits, `_` and anything outside of ASCII.
+pub(crate) fn is_word_byte(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || !c.is_ascii()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_to_find_in_an_empty_buffer() {
+        let buffer = Buffer::new();
+        for pattern in ["a", "a\\nb"] {
+            let matcher = Matcher::new(pattern, SearchOptions::default()).unwrap();
+            assert_eq!(matcher.find_forward(&buffer, Cursor::default(), true), None);
+            assert_eq!(matcher.find_forward(&buffer, Cursor::default(), false), None);
+            assert_eq!(matcher.find_backward(&buffer, Cursor::default()), None);
+            assert!(matcher.find_everywhere(&buffer).is_empty());
+        }
+    }
+
+    fn regex(pattern: &str) -> Matcher {
+        Matcher::new(pattern, SearchOptions { regex: true, ..SearchOptions::default() }).unwrap()
+    }
+
+    #[test]
+    fn matches_over_several_rows() {
+        for text in [&b"ab\ncd\nab\ncd\n"[..], b"ab\r\ncd\r\nab\r\ncd\r\n"] {
+            let buffer = Buffer::from_bytes(text);
+            let matcher = Matcher::new("b\\nc", SearchOptions::default()).unwrap();
+            let first = (Cursor::new(1, 0), Cursor::new(1, 1));
+            let second = (Cursor::new(1, 2), Cursor::new(1, 3));
+            assert_eq!(matcher.find_forward(&buffer, Cursor::default(), false), Some(first));
+            assert_eq!(matcher.find_forward(&buffer, Cursor::new(2, 0), false), Some(second));
+            assert_eq!(matcher.find_forward(&buffer, Cursor::new(2, 2), false), None);
+            assert_eq!(matcher.find_forward(&buffer, Cursor::new(2, 2), true), Some(first));
+            assert_eq!(matcher.find_backward(&buffer, Cursor::new(0, 3)), Some(second));
+            assert_eq!(matcher.find_backward(&buffer, Cursor::new(1, 2)), Some(first));
+            assert_eq!(matcher.find_backward(&buffer, Cursor::new(1, 0)), Some(second));
+            assert_eq!(matcher.find_everywhere(&buffer), [first, second]);
+            // A match on rows 0 and 1 is on row 1, and not yet on row 0 once it starts on row 2.
+            assert_eq!(matcher.find_in_rows(&buffer, 1..2), [first]);
+            assert_eq!(matcher.find_in_rows(&buffer, 3..4), [second]);
+        }
+    }
+
+    #[test]
+    fn matches_run_over_as_many_rows_as_the_pattern_has_line_breaks() {
+        let buffer = Buffer::from_bytes(b"a\n\nb\nab");
+        // With no `\n` in the pattern, `\s` has no line break to match.
+        assert!(regex("a\\s+b").find_everywhere(&buffer).is_empty());
+        let found = regex("a\\n\\n?b").find_everywhere(&buffer);
+        assert_eq!(found, [(Cursor::new(0, 0), Cursor::new(1, 2))]);
+        // `\\n` is a backslash and an `n`, not a line break.
+        assert!(regex("a\\\\n").find_everywhere(&Buffer::from_bytes(b"a\n\\n")).is_empty());
+        let found = regex("^b$\\n^a").find_everywhere(&buffer);
+        assert_eq!(found, [(Cursor::new(0, 2), Cursor::new(1, 3))]);
+    }
+
+    #[test]
+    fn replacements_over_several_rows() {
+        let buffer = Buffer::from_bytes(b"x = 1;\r\ny = 2;\r\n");
+        let matcher = regex("(\\w) = (\\d);\\n(\\w)");
+        let found = matcher.find_forward(&buffer, Cursor::default(), false).unwrap();
+        assert_eq!(found, (Cursor::new(0, 0), Cursor::new(1, 1)));
+        assert_eq!(matcher.replacement(&buffer, found, "$3$2$1"), b"y1x");
+        let literal = Matcher::new("1;\\ny", SearchOptions::default()).unwrap();
+        let found = literal.find_forward(&buffer, Cursor::default(), false).unwrap();
+        assert_eq!(literal.replacement(&buffer, found, "a\\nb"), b"a\nb");
+    }
+}
@@ -0,0 +1,175 @@
+//! Syntax highlighting, the equivalent of `editorUpdateSyntax` and the `HLDB` in the C editor.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Highlight {
+    Normal,
+    Comment,
+    MlComment, // multi line comment
+    Keyword1,
+    Keyword2,
+    String,
+    Number,
+    Match,
+}
+
+pub const HL_HIGHLIGHT_NUMBERS: u32 = 1 << 0; // 00000001
+pub const HL_HIGHLIGHT_STRINGS: u32 = 1 << 1; // 00000010
+
+#[derive(Debug)]
+pub struct Syntax {
+    pub filetype: &'static str, // file extension
+    pub filematch: &'static [&'static str], // filename
+    pub keywords: &'static [&'static str], // keywords
+    pub singleline_comment_start: &'static str, // single line comment start
+    pub multiline_comment_start: &'static str, // multi line comment start
+    pub multiline_comment_end: &'static str, // multi line comment end
+    pub flags: u32,
+}
+
+/*** filetypes ***/
+
+const C_HL_EXTENSIONS: &[&str] = &[".c", ".h", ".cpp"];
+const C_HL_KEYWORDS: &[&str] = &[
+    "switch", "if", "while", "for", "break", "continue", "return", "else",
+    "struct", "union", "typedef", "static", "enum", "class", "case",
+
+    "int|", "long|", "double|", "float|", "char|", "unsigned|", "signed|",
+    "void|",
+];
+
+pub static HLDB: &[Syntax] = &[Syntax {
+    filetype: "c",
+    filematch: C_HL_EXTENSIONS,
+    keywords: C_HL_KEYWORDS,
+    singleline_comment_start: "//",
+    multiline_comment_start: "/*",
+    multiline_comment_end: "*/",
+    flags: HL_HIGHLIGHT_NUMBERS | HL_HIGHLIGHT_STRINGS,
+}];
+
+/// Finds the `HLDB` entry whose extension or filename pattern matches `filename`.
+pub fn select(filename: &str) -> Option<&'static Syntax> {
+    let ext = filename.rfind('.').map(|i| &filename[i..]);
+
+    HLDB.iter().find(|s| {
+        s.filematch.iter().any(|&pattern| {
+            let is_ext = pattern.starts_with('.');
+            (is_ext && ext == Some(pattern)) || (!is_ext && filename.contains(pattern))
+        })
+    })
+}
+
+fn is_separator(c: u8) -> bool {
+    // '\0' stands for the end of the row, so a keyword at the very end of a line still matches.
+    c.is_ascii_whitespace() || c == b'\0' || b",.()+-/*=~%<>[];".contains(&c)
+}
+
+pub fn syntax_to_color(hl: Highlight) -> u8 {
+    match hl {
+        Highlight::Comment | Highlight::MlComment => 36, // cyan
+        Highlight::Keyword1 => 33, // yellow
+        Highlight::Keyword2 => 32, // green
+        Highlight::String => 35, // magenta
+        Highlight::Number => 31, // red
+        Highlight::Match => 34, // blue
+        Highlight::Normal => 37, // white
+    }
+}
+
+/// Highlights one rendered row into `hl` and returns whether it ends inside a multi line comment.
+pub fn highlight_row(syntax: &Syntax, render: &[u8], hl: &mut [Highlight], mut in_comment: bool) -> bool {
+    let keywords = syntax.keywords;
+
+    let scs = syntax.singleline_comment_start.as_bytes(); // single line comment start
+    let mcs = syntax.multiline_comment_start.as_bytes(); // multi line comment start
+    let mce = syntax.multiline_comment_end.as_bytes(); // multi line comment end
+
+    let mut prev_sep = true; // the beginning of the line counts as a separator
+    let mut in_string = None; // the quote character we are inside of, if any
+
+    let mut i = 0;
+    while i < render.len() {
+        let c = render[i];
+        let prev_hl = if i > 0 { hl[i - 1] } else { Highlight::Normal };
+
+        if !scs.is_empty() && in_string.is_none() && !in_comment && render[i..].starts_with(scs) {
+            hl[i..].fill(Highlight::Comment);
+            break;
+        }
+
+        if !mcs.is_empty() && !mce.is_empty() && in_string.is_none() {
+            if in_comment {
+                hl[i] = Highlight::MlComment;
+                if render[i..].starts_with(mce) {
+                    hl[i..i + mce.len()].fill(Highlight::MlComment);
+                    i += mce.len();
+                    in_comment = false;
+                    prev_sep = true;
+                } else {
+                    i += 1;
+                }
+                continue;
+            } else if render[i..].starts_with(mcs) {
+                hl[i..i + mcs.len()].fill(Highlight::MlComment);
+                i += mcs.len();
+                in_comment = true;
+                continue;
+            }
+        }
+
+        if syntax.flags & HL_HIGHLIGHT_STRINGS != 0 {
+            if let Some(quote) = in_string {
+                hl[i] = Highlight::String;
+                if c == b'\\' && i + 1 < render.len() {
+                    hl[i + 1] = Highlight::String; // highlight escape character
+                    i += 2;
+                    continue;
+                }
+                if c == quote {
+                    in_string = None;
+                }
+                i += 1;
+                prev_sep = true;
+                continue;
+            } else if c == b'"' || c == b'\'' {
+                in_string = Some(c);
+                hl[i] = Highlight::String;
+                i += 1;
+                continue;
+            }
+        }
+
+        if syntax.flags & HL_HIGHLIGHT_NUMBERS != 0
+            && ((c.is_ascii_digit() && (prev_sep || prev_hl == Highlight::Number))
+                || (c == b'.' && prev_hl == Highlight::Number))
+        {
+            hl[i] = Highlight::Number;
+            i += 1;
+            prev_sep = false;
+            continue;
+        }
+
+        if prev_sep {
+            let matched = keywords.iter().find_map(|keyword| {
+                // keywords ending with '|' are secondary keywords (types)
+                let (keyword, kind) = match keyword.strip_suffix('|') {
+                    Some(keyword) => (keyword.as_bytes(), Highlight::Keyword2),
+                    None => (keyword.as_bytes(), Highlight::Keyword1),
+                };
+                let next = render.get(i + keyword.len()).copied().unwrap_or(b'\0');
+                (render[i..].starts_with(keyword) && is_separator(next)).then_some((keyword.len(), kind))
+            });
+            if let Some((klen, kind)) = matched {
+                hl[i..i + klen].fill(kind);
+                i += klen;
+                prev_sep = false;
+                continue;
+            }
+        }
+
+        prev_sep = is_separator(c);
+        i += 1;
+    }
+
+    in_comment
+}
@@ -0,0 +1,431 @@
+//! Terminal backends. The editor only ever talks to a [`Terminal`], so the same
+//! session can run against the real TTY or against a [`VirtualTerminal`] in memory.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Stdout, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use termion::raw::{IntoRawMode, RawTerminal};
+use unicode_width::UnicodeWidthChar;
+
+use crate::clipboard;
+use crate::key::{self, Decoder, Event, Key, MouseEvent};
+
+pub trait Terminal {
+    /// Returns the size of the screen as `(rows, cols)`.
+    fn size(&self) -> io::Result<(usize, usize)>;
+
+    fn enable_raw_mode(&mut self) -> io::Result<()>;
+
+    fn disable_raw_mode(&mut self) -> io::Result<()>;
+
+    /// Writes a frame of text and escape sequences produced by [`Editor::refresh_screen`](crate::Editor::refresh_screen).
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()>;
+
+    /// Waits for the next keypress or paste and returns it.
+    fn read_event(&mut self) -> io::Result<Event>;
+
+    /// Waits up to `timeout` for the next event, and returns `None` if there was none.
+    ///
+    /// By default this waits as long as [`read_event`](Terminal::read_event) does.
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        let _ = timeout;
+        self.read_event().map(Some)
+    }
+}
+
+/*** termion ***/
+
+/// The real terminal on stdin/stdout, driven through termion.
+///
+/// Stdin is read on a thread of its own, so `read_event` can wait for the rest of an
+/// escape sequence with a timeout instead of blocking on `read()` like the C editor.
+pub struct TermionTerminal {
+    stdout: Stdout,
+    raw: Option<RawTerminal<Stdout>>, // cooked mode is restored when this is dropped
+    input: Receiver<io::Result<Vec<u8>>>,
+    decoder: Decoder,
+}
+
+impl TermionTerminal {
+    pub fn new() -> TermionTerminal {
+        let (sender, input) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stdin = io::stdin();
+            let mut buf = [0; 1024];
+            loop {
+                let result = match stdin.read(&mut buf) {
+                    Ok(0) => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stdin closed")),
+                    Ok(n) => Ok(buf[..n].to_vec()),
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => Err(err),
+                };
+                let failed = result.is_err();
+                if sender.send(result).is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        TermionTerminal {
+            stdout: io::stdout(),
+            raw: None,
+            input,
+            decoder: Decoder::new(),
+        }
+    }
+}
+
+impl Default for TermionTerminal {
+    fn default() -> Self {
+        TermionTerminal::new()
+    }
+}
+
+impl Terminal for TermionTerminal {
+    fn size(&self) -> io::Result<(usize, usize)> {
+        let (cols, rows) = termion::terminal_size()?;
+        Ok((rows as usize, cols as usize))
+    }
+
+    /// Enters raw mode and switches to the alternate screen, so the shell's scrollback is left alone.
+    /// Also turns on bracketed paste, so pasted text arrives as one [`Event::Paste`],
+    /// and SGR mouse reporting of clicks, drags and the wheel (`?1002h` and `?1006h`).
+    fn enable_raw_mode(&mut self) -> io::Result<()> {
+        if self.raw.is_none() {
+            self.raw = Some(io::stdout().into_raw_mode()?);
+            self.write_frame(b"\x1b[?1049h\x1b[?2004h")?; // switch to alternate screen, enable bracketed paste
+            self.write_frame(b"\x1b[?1002h\x1b[?1006h")?; // enable mouse reporting
+        }
+        Ok(())
+    }
+
+    fn disable_raw_mode(&mut self) -> io::Result<()> {
+        match self.raw.take() {
+            Some(raw) => {
+                self.write_frame(b"\x1b[?1006l\x1b[?1002l")?; // disable mouse reporting
+                self.write_frame(b"\x1b[?2004l\x1b[?25h\x1b[?1049l")?; // disable bracketed paste, show cursor, leave alternate screen
+                raw.suspend_raw_mode()
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.stdout.write_all(frame)?;
+        self.stdout.flush()
+    }
+
+    fn read_event(&mut self) -> io::Result<Event> {
+        self.next_event(None).map(|event| event.expect("no deadline to miss"))
+    }
+
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        self.next_event(Some(Instant::now() + timeout))
+    }
+}
+
+impl TermionTerminal {
+    /// Returns the next event, or `None` if `deadline` passes first.
+    fn next_event(&mut self, deadline: Option<Instant>) -> io::Result<Option<Event>> {
+        loop {
+            if let Some(event) = self.decoder.next_event(false) {
+                return Ok(Some(event));
+            }
+
+            let received = if !self.decoder.is_empty() {
+                // Part of an escape sequence is waiting: give the rest of it a moment to arrive.
+                self.input.recv_timeout(Duration::from_millis(key::ESCAPE_TIMEOUT_MS))
+            } else if let Some(deadline) = deadline {
+                match deadline.checked_duration_since(Instant::now()) {
+                    Some(timeout) => self.input.recv_timeout(timeout),
+                    None => return Ok(None),
+                }
+            } else {
+                self.input.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            };
+
+            match received {
+                Ok(bytes) => self.decoder.push(&bytes?),
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Some(event) = self.decoder.next_event(true) {
+                        return Ok(Some(event));
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stdin closed"));
+                }
+            }
+        }
+    }
+}
+
+/*** virtual terminal ***/
+
+/// A headless terminal that replays scripted keys and records what is drawn into a character grid.
+///
+/// Only the escape sequences the editor emits are understood: cursor position (`H`),
+/// erase in line (`K`), erase in display (`J`), cursor visibility (`?25h`/`?25l`) and setting
+/// the clipboard with OSC 52, see [`VirtualTerminal::clipboard`]. Colors and other attributes
+/// are ignored. Wide characters take two cells like they do on a real terminal, and combining
+/// characters join the cell before them. Once the scripted keys run out, `read_event` fails
+/// with `UnexpectedEof`, which ends the session.
+#[derive(Debug)]
+pub struct VirtualTerminal {
+    rows: usize,
+    cols: usize,
+    grid: Vec<Vec<String>>, // what is drawn in each cell, empty for the right half of a wide character
+    cursor: (usize, usize), // row and column, 0-based
+    cursor_visible: bool,
+    raw: bool,
+    input: VecDeque<Event>,
+    pending: Vec<u8>, // bytes of an escape sequence or UTF-8 character split across frames
+    clipboard: Option<Vec<u8>>, // the last text set with OSC 52
+}
+
+impl VirtualTerminal {
+    pub fn new(rows: usize, cols: usize) -> VirtualTerminal {
+        VirtualTerminal {
+            rows,
+            cols,
+            grid: vec![vec![" ".to_string(); cols]; rows],
+            cursor: (0, 0),
+            cursor_visible: true,
+            raw: false,
+            input: VecDeque::new(),
+            pending: Vec::new(),
+            clipboard: None,
+        }
+    }
+
+    pub fn push_key(&mut self, key: Key) {
+        self.input.push_back(Event::Key(key));
+    }
+
+    pub fn push_keys<I: IntoIterator<Item = Key>>(&mut self, keys: I) {
+        self.input.extend(keys.into_iter().map(Event::Key));
+    }
+
+    pub fn push_mouse(&mut self, mouse: MouseEvent) {
+        self.input.push_back(Event::Mouse(mouse));
+    }
+
+    /// Queues `text` as if it had been pasted with bracketed paste.
+    pub fn push_paste(&mut self, text: &str) {
+        self.input.push_back(Event::Paste(text.to_string()));
+    }
+
+    /// Queues the keys encoded in `bytes`, as if the terminal had sent them.
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        let mut decoder = Decoder::new();
+        decoder.push(bytes);
+        while let Some(event) = decoder.next_event(true) {
+            self.input.push_back(event);
+        }
+    }
+
+    /// Queues every character of `text` as a keypress, with `\n` as Enter and `\t` as Tab.
+    pub fn type_str(&mut self, text: &str) {
+        self.push_bytes(text.as_bytes());
+    }
+
+    /// Returns one row of the screen with trailing blanks removed.
+    pub fn line(&self, row: usize) -> String {
+        self.grid[row].concat().trim_end().to_string()
+    }
+
+    /// Returns every row of the screen with trailing blanks removed.
+    pub fn screen(&self) -> Vec<String> {
+        (0..self.rows).map(|row| self.line(row)).collect()
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn is_raw(&self) -> bool {
+        self.raw
+    }
+
+    /// Returns the last text the editor put on the clipboard through the terminal.
+    pub fn clipboard(&self) -> Option<&[u8]> {
+        self.clipboard.as_deref()
+    }
+
+    fn put(&mut self, c: char) {
+        let (row, col) = self.cursor;
+        if row >= self.rows {
+            return;
+        }
+        match c.width().unwrap_or(0) {
+            0 if col > 0 => self.grid[row][col - 1].push(c), // combining character
+            0 => {}
+            width => {
+                if col + width <= self.cols {
+                    // Writing over half of a wide character blanks the other half.
+                    if self.grid[row][col].is_empty() {
+                        self.grid[row][col - 1] = " ".to_string();
+                    }
+                    if let Some(next) = self.grid[row].get_mut(col + width).filter(|cell| cell.is_empty()) {
+                        *next = " ".to_string();
+                    }
+                    self.grid[row][col] = c.to_string();
+                    if width == 2 {
+                        self.grid[row][col + 1].clear();
+                    }
+                }
+                self.cursor.1 = (col + width).min(self.cols);
+            }
+        }
+    }
+
+    fn erase_in_line(&mut self, mode: usize) {
+        let (row, col) = self.cursor;
+        if row >= self.rows {
+            return;
+        }
+        let range = match mode {
+            0 => col.min(self.cols)..self.cols, // from the cursor to the end of the line
+            1 => 0..(col + 1).min(self.cols), // from the start of the line to the cursor
+            _ => 0..self.cols, // the whole line
+        };
+        self.grid[row][range].fill(" ".to_string());
+    }
+
+    fn operating_system_command(&mut self, command: &[u8]) {
+        // `52;c;<base64>` sets the clipboard.
+        if let Some(encoded) = command.strip_prefix(b"52;c;") {
+            self.clipboard = clipboard::base64_decode(encoded);
+        }
+    }
+
+    fn control_sequence(&mut self, params: &[u8], command: u8) {
+        let private = params.first() == Some(&b'?');
+        let params = if private { &params[1..] } else { params };
+        let args: Vec<usize> = params
+            .split(|&c| c == b';')
+            .map(|arg| std::str::from_utf8(arg).ok().and_then(|arg| arg.parse().ok()).unwrap_or(0))
+            .collect();
+        let arg = |i: usize| args.get(i).copied().unwrap_or(0);
+
+        match command {
+            b'H' => {
+                // positions are 1-based, and 0 means 1
+                let row = arg(0).max(1) - 1;
+                let col = arg(1).max(1) - 1;
+                self.cursor = (row.min(self.rows.saturating_sub(1)), col.min(self.cols.saturating_sub(1)));
+            }
+            b'K' => self.erase_in_line(arg(0)),
+            b'J' if arg(0) == 2 => {
+                for row in &mut self.grid {
+                    row.fill(" ".to_string());
+                }
+            }
+            b'h' | b'l' if private && arg(0) == 25 => self.cursor_visible = command == b'h',
+            _ => {} // colors and everything else
+        }
+    }
+}
+
+impl Terminal for VirtualTerminal {
+    fn size(&self) -> io::Result<(usize, usize)> {
+        Ok((self.rows, self.cols))
+    }
+
+    fn enable_raw_mode(&mut self) -> io::Result<()> {
+        self.raw = true;
+        Ok(())
+    }
+
+    fn disable_raw_mode(&mut self) -> io::Result<()> {
+        self.raw = false;
+        Ok(())
+    }
+
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        let mut bytes = std::mem::take(&mut self.pending);
+        bytes.extend_from_slice(frame);
+
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\x1b' => {
+                    if i + 1 >= bytes.len() {
+                        break; // wait for the rest of the sequence
+                    }
+                    if bytes[i + 1] == b']' {
+                        // An operating system command runs up to BEL or `ESC \`.
+                        let start = i + 2;
+                        match bytes[start..].iter().position(|&c| c == b'\x07' || c == b'\x1b') {
+                            Some(len) if bytes[start + len] == b'\x07' || start + len + 1 < bytes.len() => {
+                                self.operating_system_command(&bytes[start..start + len]);
+                                i = start + len + if bytes[start + len] == b'\x07' { 1 } else { 2 };
+                                continue;
+                            }
+                            _ => break,
+                        }
+                    }
+                    if bytes[i + 1] != b'[' {
+                        i += 2;
+                        continue;
+                    }
+                    let start = i + 2;
+                    match bytes[start..].iter().position(|&c| (0x40..=0x7e).contains(&c)) {
+                        Some(len) => {
+                            let end = start + len;
+                            self.control_sequence(&bytes[start..end], bytes[end]);
+                            i = end + 1;
+                        }
+                        None => break,
+                    }
+                }
+                b'\r' => {
+                    self.cursor.1 = 0;
+                    i += 1;
+                }
+                b'\n' => {
+                    self.cursor.0 = (self.cursor.0 + 1).min(self.rows.saturating_sub(1));
+                    i += 1;
+                }
+                c if c < 0x20 || c == 0x7f => i += 1,
+                _ => {
+                    let len = utf8_len(bytes[i]);
+                    if i + len > bytes.len() {
+                        break;
+                    }
+                    let c = std::str::from_utf8(&bytes[i..i + len])
+                        .ok()
+                        .and_then(|s| s.chars().next())
+                        .unwrap_or(char::REPLACEMENT_CHARACTER);
+                    self.put(c);
+                    i += len;
+                }
+            }
+        }
+
+        self.pending = bytes[i..].to_vec();
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> io::Result<Event> {
+        self.input
+            .pop_front()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "no more scripted keys"))
+    }
+}
+
+/// Returns the length of the UTF-8 sequence started by `first`, counting stray bytes as one.
+fn utf8_len(first: u8) -> usize {
+    match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    }
+}
@@ -0,0 +1,315 @@
+//! Windows: the screen split into views of the open buffers, one above the other or side by side.
+//!
+//! The layout is a binary tree. Its leaves are windows and every other node splits its area in
+//! two, giving the first half a number of rows or columns and the second half the rest. Each
+//! window has a cursor and scroll of its own, so two windows can show two parts of one buffer.
+
+use crate::cursor::Cursor;
+
+/// The fewest rows a window can have: one of text and its status bar.
+const MIN_ROWS: usize = 2;
+/// The fewest columns a window can have.
+const MIN_COLS: usize = 10;
+
+/// A rectangle of screen cells, counted from 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub top: usize,
+    pub left: usize,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl Rect {
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        (self.top..self.top + self.rows).contains(&row) && (self.left..self.left + self.cols).contains(&col)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Split {
+    Horizontal, // one window above the other
+    Vertical, // side by side, with a column between them
+}
+
+/// What a window shows: a buffer, by its index in the buffer list, and where it is in it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Window {
+    pub buffer: usize,
+    pub cursor: Cursor,
+    pub rowoff: usize,
+    pub coloff: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Layout {
+    Window(usize), // the index of a window
+    Split {
+        split: Split,
+        size: usize, // rows or columns of `first`
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+impl Layout {
+    /// Returns every window with the part of `area` it takes, from top left to bottom right.
+    pub fn windows(&self, area: Rect) -> Vec<(usize, Rect)> {
+        let mut windows = Vec::new();
+        self.arrange(area, &mut windows, &mut Vec::new());
+        windows
+    }
+
+    /// Returns the columns between windows side by side.
+    pub fn separators(&self, area: Rect) -> Vec<Rect> {
+        let mut separators = Vec::new();
+        self.arrange(area, &mut Vec::new(), &mut separators);
+        separators
+    }
+
+    fn arrange(&self, area: Rect, windows: &mut Vec<(usize, Rect)>, separators: &mut Vec<Rect>) {
+        match self {
+            Layout::Window(window) => windows.push((*window, area)),
+            Layout::Split { split, first, second, .. } => {
+                let (a, b) = self.halves(area);
+                if *split == Split::Vertical {
+                    separators.push(Rect { left: a.left + a.cols, cols: 1, ..area });
+                }
+                first.arrange(a, windows, separators);
+                second.arrange(b, windows, separators);
+            }
+        }
+    }
+
+    /// Returns the window at a cell of `area`, if the cell isn't between windows.
+    pub fn window_at(&self, area: Rect, row: usize, col: usize) -> Option<usize> {
+        let windows = self.windows(area);
+        windows.into_iter().find(|(_, rect)| rect.contains(row, col)).map(|(window, _)| window)
+    }
+
+    fn contains(&self, window: usize) -> bool {
+        match self {
+            Layout::Window(w) => *w == window,
+            Layout::Split { first, second, .. } => first.contains(window) || second.contains(window),
+        }
+    }
+
+    /// Returns the first window of the layout, the one at its top left.
+    fn first_window(&self) -> usize {
+        match self {
+            Layout::Window(window) => *window,
+            Layout::Split { first, .. } => first.first_window(),
+        }
+    }
+
+    /// Returns the fewest rows, or columns, the layout fits in.
+    fn min_size(&self, along: Split) -> usize {
+        match self {
+            Layout::Window(_) if along == Split::Horizontal => MIN_ROWS,
+            Layout::Window(_) => MIN_COLS,
+            Layout::Split { split, first, second, .. } if *split == along => {
+                let separator = if along == Split::Vertical { 1 } else { 0 };
+                first.min_size(along) + separator + second.min_size(along)
+            }
+            Layout::Split { first, second, .. } => first.min_size(along).max(second.min_size(along)),
+        }
+    }
+
+    /// Returns how big the first half of a split can be in `area`, leaving room for both halves.
+    fn size_bounds(&self, area: Rect) -> (usize, usize) {
+        match self {
+            Layout::Window(_) => (0, 0),
+            Layout::Split { split, first, second, .. } => {
+                let total = match split {
+                    Split::Horizontal => area.rows,
+                    Split::Vertical => area.cols.saturating_sub(1),
+                };
+                let min = first.min_size(*split);
+                (min, total.saturating_sub(second.min_size(*split)).max(min))
+            }
+        }
+    }
+
+    /// Splits `area` into the areas of the two halves of a split.
+    fn halves(&self, area: Rect) -> (Rect, Rect) {
+        let (min, max) = self.size_bounds(area);
+        match self {
+            Layout::Window(_) => (area, Rect::default()),
+            Layout::Split { split: Split::Horizontal, size, .. } => {
+                let size = (*size).clamp(min, max).min(area.rows);
+                let first = Rect { rows: size, ..area };
+                (first, Rect { top: area.top + size, rows: area.rows - size, ..area })
+            }
+            Layout::Split { size, .. } => {
+                let size = (*size).clamp(min, max).min(area.cols.saturating_sub(1));
+                let first = Rect { cols: size, ..area };
+                let rest = area.cols.saturating_sub(size + 1);
+                (first, Rect { left: area.left + size + 1, cols: rest, ..area })
+            }
+        }
+    }
+
+    /// Splits `window`, which takes `area`, in two, with `new` as the second half.
+    /// Returns `false`, leaving the layout as it was, if there isn't room for both.
+    pub fn split(&mut self, area: Rect, window: usize, split: Split, new: usize) -> bool {
+        let (rect, leaf) = match self.find(area, window) {
+            Some(found) => found,
+            None => return false,
+        };
+        let size = match split {
+            Split::Horizontal if rect.rows >= 2 * MIN_ROWS => rect.rows / 2,
+            Split::Vertical if rect.cols > 2 * MIN_COLS => (rect.cols - 1) / 2,
+            _ => return false,
+        };
+        *leaf = Layout::Split {
+            split,
+            size,
+            first: Box::new(Layout::Window(window)),
+            second: Box::new(Layout::Window(new)),
+        };
+        true
+    }
+
+    /// Returns the leaf of `window` with the part of `area` it takes.
+    fn find(&mut self, area: Rect, window: usize) -> Option<(Rect, &mut Layout)> {
+        if !self.contains(window) {
+            return None;
+        }
+        let (a, b) = self.halves(area);
+        match self {
+            Layout::Window(_) => Some((area, self)),
+            Layout::Split { first, second, .. } => match first.contains(window) {
+                true => first.find(a, window),
+                false => second.find(b, window),
+            },
+        }
+    }
+
+    /// Takes `window` out of the layout, giving its area to the other half of its split, and
+    /// renumbers the windows after it. Returns the window of that other half to move to,
+    /// or `None` if `window` is the only one.
+    pub fn remove(&mut self, window: usize) -> Option<usize> {
+        let next = self.remove_leaf(window)?;
+        self.renumber(window);
+        Some(if next > window { next - 1 } else { next })
+    }
+
+    fn remove_leaf(&mut self, window: usize) -> Option<usize> {
+        let (first, second) = match self {
+            Layout::Window(_) => return None,
+            Layout::Split { first, second, .. } => (first, second),
+        };
+        let rest = match (&**first, &**second) {
+            (Layout::Window(w), _) if *w == window => std::mem::replace(&mut **second, Layout::Window(0)),
+            (_, Layout::Window(w)) if *w == window => std::mem::replace(&mut **first, Layout::Window(0)),
+            _ if first.contains(window) => return first.remove_leaf(window),
+            _ => return second.remove_leaf(window),
+        };
+        let next = rest.first_window();
+        *self = rest;
+        Some(next)
+    }
+
+    /// Shifts down the number of every window after `removed`.
+    fn renumber(&mut self, removed: usize) {
+        match self {
+            Layout::Window(window) if *window > removed => *window -= 1,
+            Layout::Window(_) => {}
+            Layout::Split { first, second, .. } => {
+                first.renumber(removed);
+                second.renumber(removed);
+            }
+        }
+    }
+
+    /// Grows `window`, which is in `area`, by `delta` rows or columns, taking them from its
+    /// neighbour in the innermost split of the kind `along`. Returns `false` if there is none.
+    pub fn resize(&mut self, area: Rect, window: usize, along: Split, delta: isize) -> bool {
+        if !self.contains(window) {
+            return false;
+        }
+        let (a, b) = self.halves(area);
+        let (min, max) = self.size_bounds(area);
+        let (split, size, first, second) = match self {
+            Layout::Window(_) => return false,
+            Layout::Split { split, size, first, second } => (split, size, first, second),
+        };
+        if first.resize(a, window, along, delta) || second.resize(b, window, along, delta) {
+            return true;
+        }
+        if *split != along {
+            return false;
+        }
+        let delta = if first.contains(window) { delta } else { -delta };
+        *size = (*size).clamp(min, max).saturating_add_signed(delta).clamp(min, max);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rect = Rect { top: 0, left: 0, rows: 24, cols: 80 };
+
+    /// Returns window 0 above window 1, with window 2 beside window 1.
+    fn three_windows() -> Layout {
+        let mut layout = Layout::Window(0);
+        assert!(layout.split(AREA, 0, Split::Horizontal, 1));
+        assert!(layout.split(AREA, 1, Split::Vertical, 2));
+        layout
+    }
+
+    #[test]
+    fn splitting_a_window() {
+        let layout = three_windows();
+        let windows = layout.windows(AREA);
+        assert_eq!(windows, [
+            (0, Rect { rows: 12, ..AREA }),
+            (1, Rect { top: 12, rows: 12, cols: 39, ..AREA }),
+            (2, Rect { top: 12, left: 40, rows: 12, cols: 40 }),
+        ]);
+        assert_eq!(layout.separators(AREA), [Rect { top: 12, left: 39, rows: 12, cols: 1 }]);
+        assert_eq!(layout.window_at(AREA, 12, 39), None); // on the separator
+        assert_eq!(layout.window_at(AREA, 23, 79), Some(2));
+
+        // Not into windows smaller than the smallest there can be.
+        let small = Rect { rows: 2 * MIN_ROWS - 1, cols: 2 * MIN_COLS, ..AREA };
+        let mut layout = Layout::Window(0);
+        assert!(!layout.split(small, 0, Split::Horizontal, 1));
+        assert!(!layout.split(small, 0, Split::Vertical, 1));
+        assert!(!layout.split(small, 1, Split::Vertical, 2)); // no such window
+        assert_eq!(layout, Layout::Window(0));
+    }
+
+    #[test]
+    fn removing_a_window() {
+        let mut layout = three_windows();
+        // Its room goes to window 2, which becomes window 1.
+        assert_eq!(layout.remove(1), Some(1));
+        assert_eq!(layout.windows(AREA), [(0, Rect { rows: 12, ..AREA }), (1, Rect { top: 12, rows: 12, ..AREA })]);
+        assert_eq!(layout.remove(0), Some(0));
+        assert_eq!(layout, Layout::Window(0));
+        assert_eq!(layout.remove(0), None);
+    }
+
+    #[test]
+    fn resizing_a_window() {
+        let mut layout = three_windows();
+        let rows = |layout: &Layout| layout.windows(AREA).iter().map(|(_, rect)| rect.rows).collect::<Vec<_>>();
+        assert!(layout.resize(AREA, 0, Split::Horizontal, 3));
+        assert_eq!(rows(&layout), [15, 9, 9]);
+        // Window 2 is beside window 1, so it takes the rows from window 0 too.
+        assert!(layout.resize(AREA, 2, Split::Horizontal, 2));
+        assert_eq!(rows(&layout), [13, 11, 11]);
+        assert!(layout.resize(AREA, 1, Split::Vertical, 5));
+        assert_eq!(layout.windows(AREA)[1].1.cols, 44);
+        assert!(!layout.resize(AREA, 0, Split::Vertical, 1)); // nothing beside it
+
+        // No smaller or bigger than leaves room for every window.
+        assert!(layout.resize(AREA, 0, Split::Horizontal, -100));
+        assert_eq!(rows(&layout), [MIN_ROWS, 24 - MIN_ROWS, 24 - MIN_ROWS]);
+        assert!(layout.resize(AREA, 2, Split::Vertical, 100));
+        assert_eq!(layout.windows(AREA)[2].1.cols, 80 - 1 - MIN_COLS);
+    }
+}
@@ -0,0 +1,88 @@
+//! Editing sessions scripted through a `VirtualTerminal`, from the keys typed to the screen
+//! drawn and the file saved.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use tiny_editor::{Clipboard, Editor, Key, KeyCode, VirtualTerminal};
+
+/// Returns a path in the temporary directory that no other test uses.
+fn temp_file(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("tiny-editor-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn type_save_and_quit() {
+    let path = temp_file("session.txt");
+    let filename = path.to_str().unwrap();
+    let mut editor = Editor::new(10, 60);
+    let mut terminal = VirtualTerminal::new(10, 60);
+
+    terminal.type_str("Hello, world\nsecond line");
+    terminal.push_key(Key::from(KeyCode::Home));
+    terminal.type_str("a ");
+    terminal.push_key(Key::ctrl('s'));
+    terminal.type_str(filename);
+    terminal.push_key(Key::from(KeyCode::Enter));
+    // Ends with UnexpectedEof once the keys run out, leaving the last frame on the screen.
+    let err = editor.run(&mut terminal).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    let screen = terminal.screen();
+    assert_eq!(screen[0], "Hello, world");
+    assert_eq!(screen[1], "a second line");
+    assert_eq!(screen[2], "~");
+    assert!(screen[8].contains(" - 2 lines") && screen[8].ends_with("no ft | 2/2"), "{:?}", screen[8]);
+    assert_eq!(screen[9], "27 bytes written to disk");
+    assert_eq!(terminal.cursor(), (1, 2));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "Hello, world\na second line\n");
+
+    // With nothing left unsaved, Ctrl-Q quits at once and clears the screen.
+    terminal.push_key(Key::ctrl('q'));
+    editor.run(&mut terminal).unwrap();
+    assert!(terminal.screen().iter().all(|line| line.is_empty()));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn copy_and_paste_through_the_clipboard() {
+    // Through the terminal with OSC 52, with no helper commands.
+    let mut editor = Editor::new(10, 60);
+    let mut terminal = VirtualTerminal::new(10, 60);
+    terminal.type_str("first\nsecond");
+    terminal.push_key(Key::ctrl('c'));
+    let _ = editor.run(&mut terminal);
+    assert_eq!(terminal.clipboard(), Some(&b"second\n"[..]));
+
+    // Through stub helper commands instead, pasting what the paste command prints.
+    let copied = temp_file("copied.txt");
+    let mut clipboard = Clipboard {
+        osc52: false,
+        copy_command: Some(format!("cat > '{}'", copied.display())),
+        paste_command: Some("printf 'from outside'".to_string()),
+    };
+    editor.set_clipboard(clipboard.clone());
+    let mut terminal = VirtualTerminal::new(10, 60);
+    terminal.push_key(Key::from(KeyCode::Up));
+    terminal.push_key(Key::ctrl('c'));
+    terminal.push_key(Key::from(KeyCode::End));
+    terminal.push_key(Key::ctrl('v'));
+    let _ = editor.run(&mut terminal);
+    assert_eq!(terminal.clipboard(), None);
+    assert_eq!(fs::read_to_string(&copied).unwrap(), "first\n");
+    assert_eq!(terminal.line(0), "firstfrom outside");
+    assert_eq!(terminal.line(1), "second");
+
+    // A paste command that fails leaves the newest of what the editor has to paste.
+    clipboard.paste_command = Some("exit 1".to_string());
+    editor.set_clipboard(clipboard);
+    let mut terminal = VirtualTerminal::new(10, 60);
+    terminal.push_key(Key::ctrl('v'));
+    let _ = editor.run(&mut terminal);
+    assert_eq!(terminal.line(0), "firstfrom outsidefrom outside");
+    assert!(terminal.line(9).starts_with("Can't run the paste command"), "{:?}", terminal.line(9));
+    fs::remove_file(&copied).unwrap();
+}